@@ -1,10 +1,21 @@
+use std::mem::{discriminant, Discriminant};
+
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::egui;
 use bevy_rapier2d::prelude::*;
+use bevy_tnua::{TnuaGhostPlatform, TnuaProximitySensor};
 use bevy_yoleck::prelude::*;
 use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dRotatation, Vpeol3dScale};
+use bevy_yoleck::YoleckEditMarker;
+use serde::{Deserialize, Serialize};
 
+use crate::hazard::HazardActive;
 use crate::missile::ExplodesMissileOnImpact;
-use crate::utils::CachedPbrMaker;
+use crate::player::IsPlayer;
+use crate::snapping::{bounding_rect, EditorSnapping};
+use crate::utils::{resize_with_snapped_corner_knobs, CachedPbrMaker};
+use crate::{solver_groups, During};
 
 pub struct ArenaPlugin;
 
@@ -15,76 +26,195 @@ impl Plugin for ArenaPlugin {
                 .with::<Vpeol3dPosition>()
                 .with::<Vpeol3dScale>()
                 .with::<Vpeol3dRotatation>()
+                .with::<BlockSurface>()
                 .insert_on_init(|| (IsBlock, ExplodesMissileOnImpact))
         });
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("OneWayPlatform")
+                .with::<Vpeol3dPosition>()
+                .with::<Vpeol3dScale>()
+                .with::<Vpeol3dRotatation>()
+                .with::<BlockSurface>()
+                .with::<OneWayPlatform>()
+                .insert_on_init(|| IsBlock)
+        });
 
-        app.add_yoleck_edit_system(resize_block);
+        app.add_yoleck_edit_system(resize_block_with_corner_knobs);
         app.add_yoleck_edit_system(rotate_block);
+        app.add_yoleck_edit_system(snap_dragged_block);
+        app.add_yoleck_edit_system(edit_block_surface);
+        app.add_yoleck_edit_system(edit_one_way_platform);
 
         app.add_systems(YoleckSchedule::Populate, populate_block);
+        app.add_systems(
+            YoleckSchedule::Populate,
+            populate_one_way_platform.after(populate_block),
+        );
+        app.add_systems(
+            Update,
+            bounce_players_off_bouncy_blocks.in_set(During::Gameplay),
+        );
     }
 }
 
 #[derive(Component)]
 pub struct IsBlock;
 
-fn populate_block(mut populate: YoleckPopulate<(), With<IsBlock>>, mut pbr: CachedPbrMaker) {
-    populate.populate(|ctx, mut cmd, ()| {
+/// How the surface of a block affects the players (and other bodies) touching it.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub enum BlockSurface {
+    #[default]
+    Normal,
+    Ice,
+    Bouncy,
+    Sticky,
+    /// Carries whoever stands on it along the block's X axis.
+    Conveyor {
+        speed: f32,
+    },
+    /// Kills players on touch.
+    Hazard,
+}
+
+impl BlockSurface {
+    const ALL: [Self; 6] = [
+        Self::Normal,
+        Self::Ice,
+        Self::Bouncy,
+        Self::Sticky,
+        Self::Conveyor { speed: 10.0 },
+        Self::Hazard,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Normal => "Normal",
+            Self::Ice => "Ice",
+            Self::Bouncy => "Bouncy",
+            Self::Sticky => "Sticky",
+            Self::Conveyor { .. } => "Conveyor",
+            Self::Hazard => "Hazard",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Normal => Color::GRAY,
+            Self::Ice => Color::rgb(0.7, 0.9, 1.0),
+            Self::Bouncy => Color::rgb(0.3, 0.9, 0.3),
+            Self::Sticky => Color::rgb(0.6, 0.4, 0.1),
+            Self::Conveyor { .. } => Color::rgb(0.9, 0.7, 0.1),
+            Self::Hazard => Color::rgb(0.9, 0.1, 0.1),
+        }
+    }
+
+    fn friction(&self) -> Friction {
+        match self {
+            Self::Ice => Friction {
+                coefficient: 0.0,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+            Self::Sticky => Friction {
+                coefficient: 2.0,
+                combine_rule: CoefficientCombineRule::Max,
+            },
+            _ => Friction::default(),
+        }
+    }
+
+    fn restitution(&self) -> Restitution {
+        match self {
+            Self::Bouncy => Restitution {
+                coefficient: 1.0,
+                combine_rule: CoefficientCombineRule::Max,
+            },
+            _ => Restitution::default(),
+        }
+    }
+}
+
+pub fn populate_block(
+    mut populate: YoleckPopulate<&BlockSurface, With<IsBlock>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut mesh: Local<Option<Handle<Mesh>>>,
+    mut surface_materials: Local<HashMap<Discriminant<BlockSurface>, Handle<StandardMaterial>>>,
+) {
+    populate.populate(|ctx, mut cmd, surface| {
         if ctx.is_first_time() {
-            cmd.insert(pbr.make_pbr_with(
-                || Mesh::from(shape::Box::new(1.0, 1.0, 1.0)),
-                || Color::GRAY.into(),
-            ));
+            cmd.insert(PbrBundle {
+                mesh: mesh
+                    .get_or_insert_with(|| meshes.add(Mesh::from(shape::Box::new(1.0, 1.0, 1.0))))
+                    .clone(),
+                ..Default::default()
+            });
             cmd.insert(RigidBody::Fixed);
             cmd.insert(Collider::cuboid(0.5, 0.5));
         }
+        cmd.insert(
+            surface_materials
+                .entry(discriminant(surface))
+                .or_insert_with(|| materials.add(surface.color().into()))
+                .clone(),
+        );
+        cmd.insert(surface.friction());
+        cmd.insert(surface.restitution());
+        if *surface == BlockSurface::Hazard {
+            cmd.insert(HazardActive(true));
+        } else {
+            cmd.remove::<HazardActive>();
+        }
     });
 }
 
-fn resize_block(
-    mut edit: YoleckEdit<
-        (&Vpeol3dRotatation, &mut Vpeol3dScale, &mut Vpeol3dPosition),
-        With<IsBlock>,
-    >,
-    mut knobs: YoleckKnobs,
-    mut pbr: CachedPbrMaker,
-) {
-    let Ok((rotation, mut scale, mut position)) = edit.get_single_mut() else {
-        return;
-    };
-
-    let knob_pbr = pbr.make_pbr_with(
-        || Mesh::from(shape::Box::new(0.4, 0.4, 1.1)),
-        || Color::ORANGE.into(),
-    );
+/// A block that players can jump through from below, and drop through with down+jump.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct OneWayPlatform {
+    pub missiles_pass_through: bool,
+}
 
-    for (i, diagonal) in [
-        Vec2::new(1.0, 1.0),
-        Vec2::new(-1.0, 1.0),
-        Vec2::new(-1.0, -1.0),
-        Vec2::new(1.0, -1.0),
-    ]
-    .into_iter()
-    .enumerate()
-    {
-        let offset = 0.5 * diagonal * scale.0.truncate();
-        let mut knob = knobs.knob(("resize-marker", i));
-        if knob.is_new {
-            knob.cmd.insert(knob_pbr.clone());
+fn populate_one_way_platform(mut populate: YoleckPopulate<&OneWayPlatform>) {
+    populate.populate(|_ctx, mut cmd, platform| {
+        cmd.insert(TnuaGhostPlatform);
+        let mut filters = Group::ALL - solver_groups::PLAYER;
+        if platform.missiles_pass_through {
+            filters -= solver_groups::MISSILE;
+            cmd.remove::<ExplodesMissileOnImpact>();
+        } else {
+            cmd.insert(ExplodesMissileOnImpact);
         }
-        knob.cmd.insert(Transform::from_translation(
-            position.0 + rotation.0 * offset.extend(0.0),
-        ));
+        cmd.insert(SolverGroups {
+            memberships: Group::ALL,
+            filters,
+        });
+    });
+}
 
-        if let Some(new_marker_pos) = knob.get_passed_data::<Vec3>() {
-            let inverse_rotation = rotation.0.inverse();
-            let other_corner = position.0 - (inverse_rotation * offset.extend(0.0));
-            let size_f = (*new_marker_pos - other_corner).truncate();
-            let size_f = size_f * diagonal;
-            let size_f = Vec2::from_array(size_f.to_array().map(|coord| coord.max(0.0)));
-            scale.0 = size_f.extend(1.0);
-            position.0 = other_corner + 0.5 * (inverse_rotation * (diagonal * size_f).extend(0.0));
+fn edit_one_way_platform(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut OneWayPlatform>) {
+    let Ok(mut platform) = edit.get_single_mut() else {
+        return;
+    };
+    ui.checkbox(&mut platform.missiles_pass_through, "Missiles Pass Through");
+}
+
+fn edit_block_surface(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut BlockSurface, With<IsBlock>>,
+) {
+    let Ok(mut surface) = edit.get_single_mut() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Surface:");
+        for option in BlockSurface::ALL {
+            let is_selected = discriminant(surface.as_ref()) == discriminant(&option);
+            if ui.selectable_label(is_selected, option.name()).clicked() && !is_selected {
+                *surface = option;
+            }
         }
+    });
+    if let BlockSurface::Conveyor { speed } = surface.as_mut() {
+        ui.add(egui::Slider::new(speed, -20.0..=20.0).prefix("Conveyor Speed: "));
     }
 }
 
@@ -95,6 +225,7 @@ fn rotate_block(
     >,
     mut knobs: YoleckKnobs,
     mut pbr: CachedPbrMaker,
+    snapping: Res<EditorSnapping>,
 ) {
     let Ok((mut rotation, scale, position)) = edit.get_single_mut() else {
         return;
@@ -133,8 +264,108 @@ fn rotate_block(
             let desired_direction = (*new_marker_pos - position.0)
                 .truncate()
                 .normalize_or_zero();
-            let new_rotation = Quat::from_rotation_arc_2d(knob_direction, desired_direction);
-            rotation.0 = new_rotation;
+            let angle = knob_direction.angle_between(desired_direction);
+            if angle.is_finite() {
+                rotation.0 = Quat::from_rotation_z(snapping.snap_angle(angle));
+            }
+        }
+    }
+}
+
+/// Resize blocks like any other rectangle, but snap the dragged corner to the grid and to the edges
+/// of the other blocks, and the size to the grid.
+#[allow(clippy::type_complexity)]
+fn resize_block_with_corner_knobs(
+    mut edit: YoleckEdit<
+        (
+            Option<&Vpeol3dRotatation>,
+            &mut Vpeol3dScale,
+            &mut Vpeol3dPosition,
+        ),
+        With<IsBlock>,
+    >,
+    blocks_query: Query<
+        (&Vpeol3dPosition, &Vpeol3dScale, Option<&Vpeol3dRotatation>),
+        (With<IsBlock>, Without<YoleckEditMarker>),
+    >,
+    mut knobs: YoleckKnobs,
+    mut pbr: CachedPbrMaker,
+    snapping: Res<EditorSnapping>,
+    mut gizmos: Gizmos,
+) {
+    resize_with_snapped_corner_knobs(
+        &mut edit,
+        &mut knobs,
+        &mut pbr,
+        |corner| {
+            let neighbors = blocks_query
+                .iter()
+                .map(|(position, scale, rotation)| bounding_rect(position, scale, rotation))
+                .collect::<Vec<_>>();
+            snapping.snap_corner(corner, &neighbors, &mut gizmos)
+        },
+        // Rotated blocks don't end up with grid sizes from snapping their corners, so their size
+        // is snapped separately.
+        |size| snapping.snap_size(size),
+    );
+}
+
+#[allow(clippy::type_complexity)]
+fn snap_dragged_block(
+    mut edit: YoleckEdit<
+        (
+            Entity,
+            &mut Vpeol3dPosition,
+            &Vpeol3dScale,
+            Option<&Vpeol3dRotatation>,
+        ),
+        With<IsBlock>,
+    >,
+    blocks_query: Query<
+        (&Vpeol3dPosition, &Vpeol3dScale, Option<&Vpeol3dRotatation>),
+        (With<IsBlock>, Without<YoleckEditMarker>),
+    >,
+    passed_data: Res<YoleckPassedData>,
+    snapping: Res<EditorSnapping>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((entity, mut position, scale, rotation)) = edit.get_single_mut() else {
+        return;
+    };
+    if passed_data.get::<Vec3>(entity).is_none() {
+        return;
+    }
+    let neighbors = blocks_query
+        .iter()
+        .map(|(position, scale, rotation)| bounding_rect(position, scale, rotation))
+        .collect::<Vec<_>>();
+    let snapped = snapping
+        .snap_rect(
+            bounding_rect(&position, scale, rotation),
+            &neighbors,
+            &mut gizmos,
+        )
+        .extend(position.0.z);
+    if snapped != position.0 {
+        position.0 = snapped;
+    }
+}
+
+/// The upward speed players get when landing on a bouncy block.
+const BOUNCE_SPEED: f32 = 40.0;
+
+fn bounce_players_off_bouncy_blocks(
+    mut players_query: Query<(&TnuaProximitySensor, &mut Velocity), With<IsPlayer>>,
+    surfaces_query: Query<&BlockSurface>,
+) {
+    for (sensor, mut velocity) in players_query.iter_mut() {
+        let Some(ground) = sensor.output.as_ref() else {
+            continue;
+        };
+        if velocity.linvel.y <= 0.0
+            && matches!(surfaces_query.get(ground.entity), Ok(BlockSurface::Bouncy))
+        {
+            velocity.linvel.y = BOUNCE_SPEED;
         }
     }
 }
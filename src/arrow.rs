@@ -25,9 +25,6 @@ impl Plugin for ArrowPlugin {
 #[derive(Component)]
 pub struct IsArrow;
 
-#[derive(Component)]
-pub struct FireEvery(Timer);
-
 fn populate_arrow(mut populate: YoleckPopulate<(), With<IsArrow>>, asset_server: Res<AssetServer>) {
     populate.populate(|ctx, mut cmd, ()| {
         if ctx.is_first_time() {
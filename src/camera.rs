@@ -1,22 +1,67 @@
 use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::PrimaryWindow;
+use bevy_rapier2d::prelude::*;
 use bevy_yoleck::vpeol::prelude::*;
 use dolly::prelude::*;
 
-use crate::player::IsPlayer;
-use crate::During;
+use crate::camera_zone::{clamp_view_into_rect, CameraZone};
+use crate::cannon::FiringCannons;
+use crate::missile::MissileConfig;
+use crate::player::{IsPlayer, PlayerSlot};
+use crate::{AppState, During};
 
 pub struct MazeOfManyMissilesCameraPlugin;
 
 impl Plugin for MazeOfManyMissilesCameraPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<SplitScreen>();
         app.add_systems(Startup, setup_camera);
-        app.add_systems(Update, apply_dolly_camera_controls.in_set(During::Gameplay));
+        app.add_systems(
+            Update,
+            (update_split_screen, apply_dolly_camera_controls)
+                .chain()
+                .in_set(During::Gameplay),
+        );
+        app.add_systems(OnExit(AppState::Game), merge_split_screen);
     }
 }
 
+/// When enabled, each player gets their own viewport once the players are too far apart to fit
+/// in a single view.
+#[derive(Resource, Default)]
+pub struct SplitScreen {
+    pub enabled: bool,
+}
+
 #[derive(Component)]
 struct CameraController(CameraRig);
 
+#[derive(Component)]
+struct MainCamera;
+
+#[derive(Component)]
+struct PlayerCamera(Entity);
+
+const BASE_ARM_OFFSET: Vec3 = Vec3::new(0.0, 10.0, 50.0);
+
+/// The shared camera splits when it needs to be pulled back further than this to fit all the
+/// players.
+const SPLIT_AT_ARM_DISTANCE: f32 = 80.0;
+
+/// The player cameras merge back when the shared camera can fit all the players in this distance.
+/// Lower than [`SPLIT_AT_ARM_DISTANCE`] so that the view would not flicker at the threshold.
+const MERGE_AT_ARM_DISTANCE: f32 = 65.0;
+
+fn new_camera_rig(focus: Vec3) -> CameraRig {
+    CameraRig::builder()
+        .with(Position::new(focus))
+        .with(Arm::new(BASE_ARM_OFFSET))
+        .with(Smooth::new_position(1.0))
+        .with(LookAt::new(focus + 3.0 * Vec3::Y).tracking_smoothness(0.5))
+        .build()
+}
+
 fn setup_camera(mut commands: Commands) {
     let mut cmd = commands.spawn_empty();
     cmd.insert(Camera3dBundle {
@@ -26,14 +71,8 @@ fn setup_camera(mut commands: Commands) {
     });
     cmd.insert(VpeolCameraState::default());
     cmd.insert(Vpeol3dCameraControl::sidescroller());
-    cmd.insert(CameraController(
-        CameraRig::builder()
-            .with(Position::default())
-            .with(Arm::new(Vec3::new(0.0, 10.0, 50.0)))
-            .with(Smooth::new_position(1.0))
-            .with(LookAt::new(Vec3::ZERO).tracking_smoothness(0.5))
-            .build(),
-    ));
+    cmd.insert(MainCamera);
+    cmd.insert(CameraController(new_camera_rig(Vec3::ZERO)));
 
     commands.spawn(DirectionalLightBundle {
         directional_light: DirectionalLight {
@@ -47,18 +86,242 @@ fn setup_camera(mut commands: Commands) {
     });
 }
 
+/// How much space to keep around the players when they are spread apart.
+const FRAMING_MARGIN: f32 = 10.0;
+
+fn rect_around<'a>(transforms: impl Iterator<Item = &'a GlobalTransform>) -> Option<Rect> {
+    transforms.fold(None, |rect, transform| {
+        let position = transform.translation().truncate();
+        Some(match rect {
+            Some(rect) => rect.union_point(position),
+            None => Rect::from_center_size(position, Vec2::ZERO),
+        })
+    })
+}
+
+/// The distance the camera needs to be from `rect` to see all of it.
+fn arm_distance_for_framing(rect: Rect, projection: &Projection) -> f32 {
+    if let Projection::Perspective(projection) = projection {
+        let tan_half_fov = (0.5 * projection.fov).tan();
+        let half_size = 0.5 * rect.size() + FRAMING_MARGIN;
+        (half_size.x / (tan_half_fov * projection.aspect_ratio)).max(half_size.y / tan_half_fov)
+    } else {
+        0.0
+    }
+}
+
+/// The half size of the area the camera sees from the given distance.
+fn view_half_size(projection: &Projection, arm_distance: f32) -> Vec2 {
+    if let Projection::Perspective(projection) = projection {
+        let half_height = arm_distance * (0.5 * projection.fov).tan();
+        Vec2::new(half_height * projection.aspect_ratio, half_height)
+    } else {
+        Vec2::ZERO
+    }
+}
+
+fn split_viewport(rank: usize, num_players: usize, window_size: UVec2) -> Viewport {
+    // Two players get side by side viewports rather than stacked ones - missiles come from above
+    // and below, so the vertical view is the one to keep.
+    let (columns, rows) = if num_players <= 2 {
+        (num_players as u32, 1)
+    } else {
+        (2, 2)
+    };
+    let physical_size = UVec2::new(window_size.x / columns, window_size.y / rows);
+    let rank = rank as u32;
+    Viewport {
+        physical_position: UVec2::new(rank % columns, rank / columns) * physical_size,
+        physical_size,
+        ..Default::default()
+    }
+}
+
+fn update_split_screen(
+    split_screen: Res<SplitScreen>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut main_camera_query: Query<(&mut Camera, &Projection), With<MainCamera>>,
+    mut player_cameras_query: Query<(Entity, &PlayerCamera, &mut Camera), Without<MainCamera>>,
+    player_query: Query<(Entity, &GlobalTransform, &PlayerSlot), With<IsPlayer>>,
+    mut commands: Commands,
+) {
+    let Ok((mut main_camera, main_camera_projection)) = main_camera_query.get_single_mut() else {
+        return;
+    };
+
+    let mut players = player_query.iter().collect::<Vec<_>>();
+    players.sort_by_key(|(_, _, player_slot)| player_slot.index);
+
+    let was_split = !player_cameras_query.is_empty();
+    let is_split = if split_screen.enabled && 2 <= players.len() {
+        let players_rect = rect_around(players.iter().map(|(_, transform, _)| *transform))
+            .expect("there are at least two players");
+        let arm_distance = arm_distance_for_framing(players_rect, main_camera_projection);
+        if was_split {
+            MERGE_AT_ARM_DISTANCE < arm_distance
+        } else {
+            SPLIT_AT_ARM_DISTANCE < arm_distance
+        }
+    } else {
+        false
+    };
+
+    main_camera.is_active = !is_split;
+    if !is_split {
+        for (camera_entity, _, _) in player_cameras_query.iter() {
+            commands.entity(camera_entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let window_size = UVec2::new(window.physical_width(), window.physical_height());
+    for (camera_entity, player_camera, _) in player_cameras_query.iter() {
+        if !player_query.contains(player_camera.0) {
+            commands.entity(camera_entity).despawn_recursive();
+        }
+    }
+    for (rank, (player_entity, player_transform, _)) in players.iter().enumerate() {
+        let viewport = split_viewport(rank, players.len(), window_size);
+        let order = rank as isize + 1;
+        if let Some((_, _, mut camera)) = player_cameras_query
+            .iter_mut()
+            .find(|(_, player_camera, _)| player_camera.0 == *player_entity)
+        {
+            camera.viewport = Some(viewport);
+            camera.order = order;
+        } else {
+            commands.spawn((
+                Camera3dBundle {
+                    camera: Camera {
+                        viewport: Some(viewport),
+                        order,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                PlayerCamera(*player_entity),
+                CameraController(new_camera_rig(player_transform.translation())),
+            ));
+        }
+    }
+}
+
+/// The menus, the editor and the other levels are shown through the main camera alone.
+fn merge_split_screen(
+    mut main_camera_query: Query<&mut Camera, With<MainCamera>>,
+    player_cameras_query: Query<Entity, With<PlayerCamera>>,
+    mut commands: Commands,
+) {
+    for mut main_camera in main_camera_query.iter_mut() {
+        main_camera.is_active = true;
+    }
+    for camera_entity in player_cameras_query.iter() {
+        commands.entity(camera_entity).despawn_recursive();
+    }
+}
+
+/// Threats (missiles and firing cannons) closer than this to the players are kept in view.
+const THREAT_FRAMING_RADIUS: f32 = 30.0;
+
+/// How far ahead, in seconds of movement, the camera looks in the direction the players run.
+const LOOKAHEAD_SECONDS: f32 = 0.5;
+
+const MAX_LOOKAHEAD: f32 = 8.0;
+
 fn apply_dolly_camera_controls(
     time: Res<Time>,
-    mut camera_query: Query<(&mut CameraController, &mut Transform)>,
-    player_query: Query<&GlobalTransform, With<IsPlayer>>,
+    mut camera_query: Query<(
+        &mut CameraController,
+        &mut Transform,
+        &Projection,
+        Option<&PlayerCamera>,
+    )>,
+    player_query: Query<(&GlobalTransform, &Velocity), With<IsPlayer>>,
+    missiles_query: Query<&GlobalTransform, With<MissileConfig>>,
+    firing_cannons: FiringCannons,
+    camera_zones_query: Query<(&CameraZone, &GlobalTransform)>,
 ) {
-    let Ok(player_transform) = player_query.get_single() else {
+    let Some(players_rect) = rect_around(player_query.iter().map(|(transform, _)| transform))
+    else {
         return;
     };
-    let player_position = player_transform.translation();
-    for (mut camera_controller, mut camera_transform) in camera_query.iter_mut() {
-        camera_controller.0.driver_mut::<Position>().position = player_position;
-        camera_controller.0.driver_mut::<LookAt>().target = player_position + 3.0 * Vec3::Y;
+    let players_running_velocity = player_query
+        .iter()
+        .map(|(_, velocity)| velocity.linvel.x)
+        .sum::<f32>()
+        / player_query.iter().len() as f32;
+    let camera_zones = camera_zones_query
+        .iter()
+        .map(|(camera_zone, transform)| {
+            let (scale, _, translation) = transform.to_scale_rotation_translation();
+            (
+                camera_zone,
+                Rect::from_center_size(translation.truncate(), scale.truncate()),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (mut camera_controller, mut camera_transform, projection, player_camera) in
+        camera_query.iter_mut()
+    {
+        let (players_rect, running_velocity) = if let Some(player_camera) = player_camera {
+            let Ok((player_transform, player_velocity)) = player_query.get(player_camera.0) else {
+                continue;
+            };
+            (
+                Rect::from_center_size(player_transform.translation().truncate(), Vec2::ZERO),
+                player_velocity.linvel.x,
+            )
+        } else {
+            (players_rect, players_running_velocity)
+        };
+        let framed_rect = missiles_query
+            .iter()
+            .map(|transform| transform.translation().truncate())
+            .chain(firing_cannons.positions())
+            .filter(|threat_position| {
+                threat_position.distance(threat_position.clamp(players_rect.min, players_rect.max))
+                    < THREAT_FRAMING_RADIUS
+            })
+            .fold(players_rect, |rect, threat_position| {
+                rect.union_point(threat_position)
+            });
+        let framing_distance = arm_distance_for_framing(framed_rect, projection);
+
+        let mut center = framed_rect.center();
+        center.x += (LOOKAHEAD_SECONDS * running_velocity).clamp(-MAX_LOOKAHEAD, MAX_LOOKAHEAD);
+        let mut arm_distance = framing_distance.max(BASE_ARM_OFFSET.z);
+
+        let mut total_weight = 0.0;
+        let mut zones_center = Vec2::ZERO;
+        let mut zones_arm_distance = 0.0;
+        for (camera_zone, zone_rect) in camera_zones.iter() {
+            let weight = camera_zone.weight_at(*zone_rect, center);
+            if weight <= 0.0 {
+                continue;
+            }
+            let zone_arm_distance = camera_zone.arm_distance.max(framing_distance);
+            total_weight += weight;
+            zones_center += weight
+                * clamp_view_into_rect(
+                    *zone_rect,
+                    view_half_size(projection, zone_arm_distance),
+                    center,
+                );
+            zones_arm_distance += weight * zone_arm_distance;
+        }
+        if 0.0 < total_weight {
+            let blend = total_weight.min(1.0);
+            center = center.lerp(zones_center / total_weight, blend);
+            arm_distance += blend * (zones_arm_distance / total_weight - arm_distance);
+        }
+
+        camera_controller.0.driver_mut::<Arm>().offset =
+            Vec3::new(BASE_ARM_OFFSET.x, BASE_ARM_OFFSET.y, arm_distance);
+        camera_controller.0.driver_mut::<Position>().position = center.extend(0.0);
+        camera_controller.0.driver_mut::<LookAt>().target = center.extend(0.0) + 3.0 * Vec3::Y;
         camera_controller.0.update(time.delta_seconds());
         camera_transform.translation = camera_controller.0.final_transform.position;
         camera_transform.rotation = camera_controller.0.final_transform.rotation;
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dScale};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{keep_zone_behind_geometry, resize_with_corner_knobs, CachedPbrMaker};
+
+pub struct CameraZonePlugin;
+
+impl Plugin for CameraZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("CameraZone")
+                .with::<Vpeol3dPosition>()
+                .with::<Vpeol3dScale>()
+                .with::<CameraZone>()
+        });
+
+        app.add_yoleck_edit_system(resize_with_corner_knobs::<With<CameraZone>>);
+        app.add_yoleck_edit_system(keep_zone_behind_geometry::<With<CameraZone>>);
+        app.add_yoleck_edit_system(edit_camera_zone);
+
+        app.add_systems(YoleckSchedule::Populate, populate_camera_zone);
+    }
+}
+
+/// A rectangle the camera is kept inside of while it follows a player through it.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct CameraZone {
+    /// How far the camera is from the players while inside the zone.
+    pub arm_distance: f32,
+    /// How deep into the zone the camera needs to go before the zone fully takes over.
+    pub blend_distance: f32,
+}
+
+impl Default for CameraZone {
+    fn default() -> Self {
+        Self {
+            arm_distance: 50.0,
+            blend_distance: 5.0,
+        }
+    }
+}
+
+fn populate_camera_zone(
+    mut populate: YoleckPopulate<(), With<CameraZone>>,
+    mut pbr: CachedPbrMaker,
+) {
+    populate.populate(|ctx, mut cmd, ()| {
+        if ctx.is_in_editor() && ctx.is_first_time() {
+            cmd.insert(pbr.make_pbr_with(
+                || Mesh::from(shape::Box::new(1.0, 1.0, 0.01)),
+                || StandardMaterial {
+                    base_color: Color::rgba(0.3, 0.6, 1.0, 0.1),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..Default::default()
+                },
+            ));
+        }
+    });
+}
+
+fn edit_camera_zone(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut CameraZone>) {
+    let Ok(mut camera_zone) = edit.get_single_mut() else {
+        return;
+    };
+    ui.add(
+        egui::Slider::new(&mut camera_zone.arm_distance, 10.0..=200.0).prefix("Camera Distance: "),
+    );
+    ui.add(
+        egui::Slider::new(&mut camera_zone.blend_distance, 0.0..=50.0).prefix("Blend Distance: "),
+    );
+}
+
+impl CameraZone {
+    /// How much this zone affects the camera when it looks at `point`, from `0.0` (not at all) to
+    /// `1.0` (fully).
+    pub fn weight_at(&self, rect: Rect, point: Vec2) -> f32 {
+        let depth = (point - rect.min).min(rect.max - point).min_element();
+        if depth < 0.0 {
+            0.0
+        } else if self.blend_distance <= 0.0 {
+            1.0
+        } else {
+            let t = (depth / self.blend_distance).min(1.0);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+/// Move the point the camera looks at so that a view of the given half size stays inside `rect`.
+pub fn clamp_view_into_rect(rect: Rect, view_half_size: Vec2, point: Vec2) -> Vec2 {
+    let min = rect.min + view_half_size;
+    let max = rect.max - view_half_size;
+    Vec2::from_array([0, 1].map(|axis| {
+        if min[axis] <= max[axis] {
+            point[axis].clamp(min[axis], max[axis])
+        } else {
+            0.5 * (rect.min[axis] + rect.max[axis])
+        }
+    }))
+}
@@ -1,12 +1,30 @@
+use std::mem::discriminant;
+
+use bevy::ecs::event::ManualEventReader;
+use bevy::ecs::query::ReadOnlyWorldQuery;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::utils::{HashMap, Uuid};
+use bevy_egui::egui;
+use bevy_rapier2d::prelude::*;
 use bevy_turborand::{DelegatedRng, GlobalRng};
+use bevy_yoleck::exclusive_systems::{YoleckExclusiveSystemDirective, YoleckExclusiveSystemsQueue};
 use bevy_yoleck::prelude::*;
-use bevy_yoleck::vpeol::VpeolWillContainClickableChildren;
+use bevy_yoleck::vpeol::{vpeol_read_click_on_entity, VpeolWillContainClickableChildren};
 use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dRotatation};
+use bevy_yoleck::{yoleck_exclusive_system_cancellable, yoleck_map_entity_to_uuid};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
 
-use crate::missile::LaunchMissile;
+use crate::explosion::{ExplosionConfig, ExplosionFalloff, StartExplosion};
+use crate::logic::{IsSwitch, IsTriggerZone, LogicInputs, LogicPowered, LogicSignal};
+use crate::missile::{
+    simulate_missile_path, ExplodesMissileOnImpact, LaunchMissile, MissileConfig, MissileFuse,
+    MissileKind,
+};
+use crate::player::IsPlayer;
 use crate::utils::CachedPbrMaker;
-use crate::During;
+use crate::{AppState, During};
 
 pub struct CannonPlugin;
 
@@ -16,12 +34,43 @@ impl Plugin for CannonPlugin {
             YoleckEntityType::new("Cannon")
                 .with::<Vpeol3dPosition>()
                 .with::<Vpeol3dRotatation>()
+                .with::<LogicInputs>()
+                .with::<CannonTurret>()
+                .with::<CannonActivation>()
+                .with::<CannonDurability>()
+                .with::<MissileKind>()
+                .with::<MissileFuse>()
                 .insert_on_init(|| IsCannon)
         });
 
         app.add_systems(YoleckSchedule::Populate, populate_cannon);
         app.add_yoleck_edit_system(edit_cannon_direction);
-        app.add_systems(Update, cannons_fire_missiles.in_set(During::Gameplay));
+        app.add_yoleck_edit_system(edit_cannon_turret);
+        app.add_yoleck_edit_system(edit_cannon_activation);
+        app.add_yoleck_edit_system(edit_cannon_durability);
+        app.add_yoleck_edit_system(edit_cannon_missile_kind);
+        app.add_yoleck_edit_system(edit_cannon_missile_fuse);
+        app.add_yoleck_edit_system(preview_cannon_missile_paths);
+        app.add_systems(
+            Update,
+            draw_cannon_activations.run_if(in_state(AppState::Editor)),
+        );
+        app.add_systems(
+            Update,
+            (
+                activate_cannons,
+                aim_turrets,
+                cannons_fire_missiles,
+                fire_charged_shots,
+                draw_charging_shots,
+            )
+                .chain()
+                .in_set(During::Gameplay),
+        );
+        app.add_systems(
+            Update,
+            (damage_cannons, draw_cannon_hit_points).in_set(During::Gameplay),
+        );
     }
 }
 
@@ -31,12 +80,159 @@ pub struct IsCannon;
 #[derive(Component)]
 pub struct FireEvery(Timer);
 
+/// Makes the cannon turn toward the players instead of always firing in the direction it was placed
+/// in.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct CannonTurret {
+    pub enabled: bool,
+    /// How far the turret can turn, centered on the direction it was placed in.
+    pub arc_degrees: f32,
+    pub turn_speed_degrees: f32,
+    /// How long the turret telegraphs each shot before firing it.
+    pub charge_seconds: f32,
+}
+
+impl Default for CannonTurret {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            arc_degrees: 90.0,
+            turn_speed_degrees: 90.0,
+            charge_seconds: 0.5,
+        }
+    }
+}
+
+/// Lets explosions destroy the cannon.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct CannonDurability {
+    /// How many explosions the cannon can take. If `None`, the cannon cannot be destroyed.
+    pub hit_points: Option<u32>,
+}
+
+/// How many more explosions a destructible cannon can take.
+#[derive(Component)]
+pub struct CannonHitPoints(u32);
+
+const CANNON_RADIUS: f32 = 1.0;
+
+/// Destroyed cannons go off with a bigger blast than a missile.
+const CANNON_DESTRUCTION_EXPLOSION: ExplosionConfig = ExplosionConfig {
+    radius: 8.0,
+    duration: 0.5,
+    force: 1500.0,
+    falloff: ExplosionFalloff::Linear,
+};
+
+/// When the cannon starts firing, so that missiles won't pile up in parts of the level the players
+/// haven't reached yet.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub enum CannonActivation {
+    #[default]
+    Always,
+    /// Only fires while a player is within `radius` of the cannon.
+    PlayerWithinRadius { radius: f32 },
+    /// Starts firing once a player enters the trigger zone.
+    TriggerZone { zone: Option<Uuid> },
+    /// Starts firing once the switch is turned on, and keeps firing even if it is turned off.
+    AfterSwitch { switch: Option<Uuid> },
+}
+
+impl CannonActivation {
+    const ALL: [CannonActivation; 4] = [
+        CannonActivation::Always,
+        CannonActivation::PlayerWithinRadius { radius: 20.0 },
+        CannonActivation::TriggerZone { zone: None },
+        CannonActivation::AfterSwitch { switch: None },
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            CannonActivation::Always => "Always",
+            CannonActivation::PlayerWithinRadius { .. } => "Player Nearby",
+            CannonActivation::TriggerZone { .. } => "Trigger Zone",
+            CannonActivation::AfterSwitch { .. } => "After Switch",
+        }
+    }
+
+    /// The trigger zone or switch the activation depends on.
+    fn source(&self) -> Option<Uuid> {
+        match self {
+            CannonActivation::Always | CannonActivation::PlayerWithinRadius { .. } => None,
+            CannonActivation::TriggerZone { zone: source }
+            | CannonActivation::AfterSwitch { switch: source } => *source,
+        }
+    }
+}
+
+/// Whether the cannon's [`CannonActivation`] allows it to fire.
+#[derive(Component)]
+struct CannonActive(bool);
+
+fn is_firing(active: &CannonActive, powered: Option<&LogicPowered>) -> bool {
+    active.0 && matches!(powered, None | Some(LogicPowered(true)))
+}
+
+/// The cannons that are currently firing - activated, and powered if they are wired.
+#[derive(SystemParam)]
+pub struct FiringCannons<'w, 's> {
+    query: Query<
+        'w,
+        's,
+        (
+            &'static GlobalTransform,
+            &'static CannonActive,
+            Option<&'static LogicPowered>,
+        ),
+        With<FireEvery>,
+    >,
+}
+
+impl FiringCannons<'_, '_> {
+    pub fn positions(&self) -> impl '_ + Iterator<Item = Vec2> {
+        self.query
+            .iter()
+            .filter(|(_, active, powered)| is_firing(active, *powered))
+            .map(|(transform, _, _)| transform.translation().truncate())
+    }
+}
+
+/// The direction a turret is currently aimed at, as an angle from the direction it was placed in.
+#[derive(Component)]
+struct TurretAim {
+    base_direction: Vec2,
+    offset: f32,
+    /// Whether the turret is aimed at a player it can see.
+    on_target: bool,
+}
+
+/// A turret shot that is charging up, and will be fired when the timer finishes.
+#[derive(Component)]
+struct ChargingShot(Timer);
+
+/// How close (in radians) a turret needs to be aimed at a player before it shoots.
+const TURRET_AIM_TOLERANCE: f32 = 0.1;
+
+/// How far in front of the cannon the missiles are launched from.
+const MISSILE_LAUNCH_OFFSET: f32 = 1.5;
+
+/// How many seconds of missile flight are shown when editing a cannon.
+const MISSILE_PATH_PREVIEW_DURATION: f32 = 3.0;
+
 fn populate_cannon(
-    mut populate: YoleckPopulate<(), With<IsCannon>>,
+    mut populate: YoleckPopulate<
+        (
+            &Vpeol3dRotatation,
+            &CannonTurret,
+            &CannonActivation,
+            &CannonDurability,
+        ),
+        With<IsCannon>,
+    >,
     asset_server: Res<AssetServer>,
     mut rng: ResMut<GlobalRng>,
 ) {
-    populate.populate(|ctx, mut cmd, ()| {
+    populate.populate(|ctx, mut cmd, (rotation, turret, activation, durability)| {
         if ctx.is_first_time() {
             cmd.insert(VpeolWillContainClickableChildren);
             cmd.insert(SceneBundle {
@@ -48,6 +244,20 @@ fn populate_cannon(
             let mut timer = Timer::from_seconds(0.5, TimerMode::Repeating);
             timer.tick(timer.duration().mul_f32(rng.f32()));
             cmd.insert(FireEvery(timer));
+            cmd.insert(CannonActive(*activation == CannonActivation::Always));
+            if turret.enabled {
+                cmd.insert(TurretAim {
+                    base_direction: (rotation.0 * Vec3::NEG_Z).truncate().normalize_or_zero(),
+                    offset: 0.0,
+                    on_target: false,
+                });
+            }
+            if let Some(hit_points) = durability.hit_points {
+                cmd.insert(CannonHitPoints(hit_points));
+                cmd.insert(Collider::ball(CANNON_RADIUS));
+                cmd.insert(Sensor);
+                cmd.insert(ExplodesMissileOnImpact);
+            }
         }
     });
 }
@@ -91,19 +301,472 @@ fn edit_cannon_direction(
     }
 }
 
+fn edit_cannon_turret(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<(&mut CannonTurret, &Vpeol3dRotatation, &Vpeol3dPosition), With<IsCannon>>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((mut turret, rotation, position)) = edit.get_single_mut() else {
+        return;
+    };
+    ui.checkbox(&mut turret.enabled, "Turret");
+    if !turret.enabled {
+        return;
+    }
+    ui.add(egui::Slider::new(&mut turret.arc_degrees, 0.0..=360.0).prefix("Arc: "));
+    ui.add(egui::Slider::new(&mut turret.turn_speed_degrees, 10.0..=360.0).prefix("Turn Speed: "));
+    ui.add(egui::Slider::new(&mut turret.charge_seconds, 0.0..=2.0).prefix("Charge Seconds: "));
+
+    let base_direction = (rotation.0 * Vec3::NEG_Z).truncate().normalize_or_zero();
+    let half_arc = 0.5 * turret.arc_degrees.to_radians();
+    for edge in [-half_arc, half_arc] {
+        gizmos.line_2d(
+            position.0.truncate(),
+            position.0.truncate() + 3.0 * Vec2::from_angle(edge).rotate(base_direction),
+            Color::ORANGE,
+        );
+    }
+}
+
+fn edit_cannon_activation(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut CannonActivation, With<IsCannon>>,
+    mut exclusive_queue: ResMut<YoleckExclusiveSystemsQueue>,
+) {
+    let Ok(mut activation) = edit.get_single_mut() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Activation:");
+        for option in CannonActivation::ALL {
+            let is_selected = discriminant(activation.as_ref()) == discriminant(&option);
+            if ui.selectable_label(is_selected, option.name()).clicked() && !is_selected {
+                *activation = option;
+            }
+        }
+    });
+    let source_name = match activation.as_mut() {
+        CannonActivation::Always => return,
+        CannonActivation::PlayerWithinRadius { radius } => {
+            ui.add(egui::Slider::new(radius, 1.0..=100.0).prefix("Radius: "));
+            return;
+        }
+        CannonActivation::TriggerZone { .. } => "Trigger Zone",
+        CannonActivation::AfterSwitch { .. } => "Switch",
+    };
+    ui.horizontal(|ui| {
+        let button = if let Some(source) = activation.source() {
+            ui.button(format!("{}: {}", source_name, source.simple()))
+        } else {
+            ui.button(format!("No {}", source_name))
+        };
+        if button.clicked() {
+            if matches!(*activation, CannonActivation::TriggerZone { .. }) {
+                pick_activation_source::<With<IsTriggerZone>>(&mut exclusive_queue);
+            } else {
+                pick_activation_source::<With<IsSwitch>>(&mut exclusive_queue);
+            }
+        }
+    });
+}
+
+fn edit_cannon_durability(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut CannonDurability, With<IsCannon>>,
+) {
+    let Ok(mut durability) = edit.get_single_mut() else {
+        return;
+    };
+    let mut destructible = durability.hit_points.is_some();
+    ui.checkbox(&mut destructible, "Destructible");
+    if destructible != durability.hit_points.is_some() {
+        durability.hit_points = destructible.then_some(1);
+    }
+    if let Some(hit_points) = durability.hit_points.as_mut() {
+        ui.add(egui::Slider::new(hit_points, 1..=10).prefix("Hit Points: "));
+    }
+}
+
+fn edit_cannon_missile_kind(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut MissileKind, With<IsCannon>>,
+) {
+    let Ok(mut missile_kind) = edit.get_single_mut() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Missiles:");
+        for option in MissileKind::ALL {
+            let is_selected = discriminant(missile_kind.as_ref()) == discriminant(&option);
+            if ui.selectable_label(is_selected, option.name()).clicked() && !is_selected {
+                *missile_kind = option;
+            }
+        }
+    });
+    if let MissileKind::Cluster {
+        split_after,
+        split_distance,
+        fragments,
+    } = missile_kind.as_mut()
+    {
+        ui.add(egui::Slider::new(split_after, 0.1..=5.0).prefix("Split After: "));
+        ui.add(egui::Slider::new(split_distance, 0.0..=30.0).prefix("Split Distance: "));
+        ui.add(egui::Slider::new(fragments, 1..=8).prefix("Fragments: "));
+    }
+}
+
+fn edit_cannon_missile_fuse(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut MissileFuse, With<IsCannon>>,
+) {
+    let Ok(mut fuse) = edit.get_single_mut() else {
+        return;
+    };
+    let mut has_proximity_fuse = fuse.proximity.is_some();
+    ui.checkbox(&mut has_proximity_fuse, "Proximity Fuse");
+    if has_proximity_fuse != fuse.proximity.is_some() {
+        fuse.proximity = has_proximity_fuse.then_some(4.0);
+    }
+    if let Some(proximity) = fuse.proximity.as_mut() {
+        ui.add(egui::Slider::new(proximity, 1.0..=15.0).prefix("Fuse Distance: "));
+        ui.add(egui::Slider::new(&mut fuse.delay, 0.0..=2.0).prefix("Fuse Delay: "));
+    }
+}
+
+fn pick_activation_source<F: 'static + ReadOnlyWorldQuery>(
+    exclusive_queue: &mut YoleckExclusiveSystemsQueue,
+) {
+    exclusive_queue.push_back(
+        vpeol_read_click_on_entity::<F>
+            .pipe(yoleck_map_entity_to_uuid)
+            .pipe(
+                |In(picked): In<Option<Uuid>>, mut edit: YoleckEdit<&mut CannonActivation>| {
+                    let Ok(mut activation) = edit.get_single_mut() else {
+                        return YoleckExclusiveSystemDirective::Finished;
+                    };
+                    let Some(picked) = picked else {
+                        return YoleckExclusiveSystemDirective::Listening;
+                    };
+                    match activation.as_mut() {
+                        CannonActivation::TriggerZone { zone: source }
+                        | CannonActivation::AfterSwitch { switch: source } => {
+                            *source = Some(picked);
+                        }
+                        _ => {}
+                    }
+                    YoleckExclusiveSystemDirective::Finished
+                },
+            )
+            .pipe(yoleck_exclusive_system_cancellable),
+    );
+}
+
+fn draw_cannon_activations(
+    cannons_query: Query<(&CannonActivation, &GlobalTransform, &YoleckBelongsToLevel)>,
+    sources_query: Query<(&YoleckEntityUuid, &GlobalTransform, &YoleckBelongsToLevel)>,
+    mut gizmos: Gizmos,
+) {
+    let source_positions = sources_query
+        .iter()
+        .map(|(uuid, transform, belongs_to_level)| {
+            (
+                (belongs_to_level.level, uuid.get()),
+                transform.translation(),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    for (activation, transform, belongs_to_level) in cannons_query.iter() {
+        if let CannonActivation::PlayerWithinRadius { radius } = activation {
+            gizmos.circle_2d(transform.translation().truncate(), *radius, Color::ORANGE);
+        }
+        if let Some(source_position) = activation
+            .source()
+            .and_then(|source| source_positions.get(&(belongs_to_level.level, source)))
+        {
+            gizmos.line(transform.translation(), *source_position, Color::ORANGE);
+        }
+    }
+}
+
+fn preview_cannon_missile_paths(
+    mut edit: YoleckEdit<(&Vpeol3dRotatation, &Vpeol3dPosition, &MissileKind), With<IsCannon>>,
+    players_query: Query<&Vpeol3dPosition, With<IsPlayer>>,
+    mut gizmos: Gizmos,
+) {
+    let player_positions = players_query
+        .iter()
+        .map(|position| position.0.truncate())
+        .collect::<Vec<_>>();
+    for (cannon_rotation, cannon_position, missile_kind) in edit.iter_matching() {
+        let direction = (cannon_rotation.0 * Vec3::NEG_Z)
+            .truncate()
+            .normalize_or_zero();
+        let path = simulate_missile_path(
+            &missile_kind.config(),
+            cannon_position.0.truncate() + direction * MISSILE_LAUNCH_OFFSET,
+            direction,
+            &player_positions,
+            MISSILE_PATH_PREVIEW_DURATION,
+            1.0 / 60.0,
+        );
+        gizmos.linestrip(
+            path.into_iter()
+                .map(|point| point.extend(cannon_position.0.z)),
+            Color::ORANGE_RED,
+        );
+    }
+}
+
+fn activate_cannons(
+    mut cannons_query: Query<(
+        &CannonActivation,
+        &mut CannonActive,
+        &GlobalTransform,
+        &YoleckBelongsToLevel,
+    )>,
+    players_query: Query<&GlobalTransform, With<IsPlayer>>,
+    sources_query: Query<(&YoleckEntityUuid, &YoleckBelongsToLevel, &LogicSignal)>,
+) {
+    for (activation, mut active, transform, belongs_to_level) in cannons_query.iter_mut() {
+        let new_active = match activation {
+            CannonActivation::Always => true,
+            CannonActivation::PlayerWithinRadius { radius } => {
+                players_query.iter().any(|player_transform| {
+                    player_transform
+                        .translation()
+                        .truncate()
+                        .distance(transform.translation().truncate())
+                        < *radius
+                })
+            }
+            CannonActivation::TriggerZone { zone: source }
+            | CannonActivation::AfterSwitch { switch: source } => {
+                active.0
+                    || sources_query
+                        .iter()
+                        .any(|(uuid, source_belongs_to_level, signal)| {
+                            Some(uuid.get()) == *source
+                                && source_belongs_to_level.level == belongs_to_level.level
+                                && signal.0
+                        })
+            }
+        };
+        if active.0 != new_active {
+            active.0 = new_active;
+        }
+    }
+}
+
+fn aim_turrets(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut turrets_query: Query<(
+        &CannonTurret,
+        &mut TurretAim,
+        &mut Transform,
+        &GlobalTransform,
+    )>,
+    players_query: Query<&GlobalTransform, With<IsPlayer>>,
+    missiles_query: Query<(), With<MissileConfig>>,
+) {
+    for (turret, mut aim, mut transform, global_transform) in turrets_query.iter_mut() {
+        let position = global_transform.translation().truncate();
+        let half_arc = 0.5 * turret.arc_degrees.to_radians();
+        let has_line_of_sight = |target: Vec2| {
+            let to_target = target - position;
+            let hit = rapier_context.cast_ray(
+                position,
+                to_target.normalize_or_zero(),
+                to_target.length(),
+                true,
+                QueryFilter::new()
+                    .exclude_sensors()
+                    .predicate(&|entity| !missiles_query.contains(entity)),
+            );
+            hit.is_some_and(|(entity, _)| players_query.contains(entity))
+        };
+        let target_offset = players_query
+            .iter()
+            .map(|player_transform| player_transform.translation().truncate())
+            .filter_map(|player_position| {
+                let offset = aim.base_direction.angle_between(player_position - position);
+                (offset.abs() <= half_arc && has_line_of_sight(player_position))
+                    .then_some((offset, player_position.distance_squared(position)))
+            })
+            .min_by_key(|(_, distance_squared)| OrderedFloat(*distance_squared))
+            .map(|(offset, _)| offset);
+
+        if let Some(target_offset) = target_offset {
+            let max_turn = turret.turn_speed_degrees.to_radians() * time.delta_seconds();
+            aim.offset += (target_offset - aim.offset).clamp(-max_turn, max_turn);
+            aim.on_target = (target_offset - aim.offset).abs() < TURRET_AIM_TOLERANCE;
+        } else {
+            aim.on_target = false;
+        }
+        let direction = Vec2::from_angle(aim.offset).rotate(aim.base_direction);
+        transform.rotation = Quat::from_rotation_arc(Vec3::NEG_Z, direction.extend(0.0));
+    }
+}
+
+fn launch_from_cannon(
+    cannon_entity: Entity,
+    transform: &GlobalTransform,
+    level: Entity,
+    kind: MissileKind,
+    fuse: MissileFuse,
+) -> LaunchMissile {
+    let direction = transform.forward().truncate().normalize_or_zero();
+    LaunchMissile {
+        level,
+        position: transform.translation().truncate() + direction * MISSILE_LAUNCH_OFFSET,
+        direction,
+        kind,
+        fuse,
+        launcher: Some(cannon_entity),
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn cannons_fire_missiles(
     time: Res<Time>,
-    mut query: Query<(&mut FireEvery, &GlobalTransform, &YoleckBelongsToLevel)>,
+    mut query: Query<(
+        Entity,
+        &mut FireEvery,
+        &GlobalTransform,
+        &YoleckBelongsToLevel,
+        &CannonActive,
+        Option<&LogicPowered>,
+        Option<(&CannonTurret, &TurretAim)>,
+        Has<ChargingShot>,
+        (&MissileKind, &MissileFuse),
+    )>,
     mut writer: EventWriter<LaunchMissile>,
+    mut commands: Commands,
 ) {
-    for (mut fire_every, transform, belongs_to_level) in query.iter_mut() {
-        if fire_every.0.tick(time.delta()).just_finished() {
-            let direction = transform.forward().truncate().normalize_or_zero();
-            writer.send(LaunchMissile {
-                level: belongs_to_level.level,
-                position: transform.translation().truncate() + direction * 1.5,
-                direction,
-            });
+    for (
+        entity,
+        mut fire_every,
+        transform,
+        belongs_to_level,
+        active,
+        powered,
+        turret,
+        charging,
+        (missile_kind, missile_fuse),
+    ) in query.iter_mut()
+    {
+        if charging || !is_firing(active, powered) {
+            continue;
+        }
+        if !fire_every.0.tick(time.delta()).just_finished() {
+            continue;
+        }
+        match turret {
+            None => writer.send(launch_from_cannon(
+                entity,
+                transform,
+                belongs_to_level.level,
+                *missile_kind,
+                *missile_fuse,
+            )),
+            // Turrets hold their fire until they have a clear shot.
+            Some((_, aim)) if !aim.on_target => {}
+            Some((turret, _)) => {
+                commands
+                    .entity(entity)
+                    .insert(ChargingShot(Timer::from_seconds(
+                        turret.charge_seconds,
+                        TimerMode::Once,
+                    )));
+            }
+        }
+    }
+}
+
+fn fire_charged_shots(
+    time: Res<Time>,
+    mut query: Query<(
+        Entity,
+        &mut ChargingShot,
+        &GlobalTransform,
+        &YoleckBelongsToLevel,
+        &MissileKind,
+        &MissileFuse,
+    )>,
+    mut writer: EventWriter<LaunchMissile>,
+    mut commands: Commands,
+) {
+    for (entity, mut charging_shot, transform, belongs_to_level, missile_kind, missile_fuse) in
+        query.iter_mut()
+    {
+        if charging_shot.0.tick(time.delta()).finished() {
+            writer.send(launch_from_cannon(
+                entity,
+                transform,
+                belongs_to_level.level,
+                *missile_kind,
+                *missile_fuse,
+            ));
+            commands.entity(entity).remove::<ChargingShot>();
+        }
+    }
+}
+
+fn draw_charging_shots(query: Query<(&ChargingShot, &GlobalTransform)>, mut gizmos: Gizmos) {
+    for (charging_shot, transform) in query.iter() {
+        let direction = transform.forward().truncate().normalize_or_zero();
+        let muzzle = transform.translation().truncate() + direction * MISSILE_LAUNCH_OFFSET;
+        let progress = charging_shot.0.percent();
+        gizmos.circle_2d(muzzle, 1.0 - 0.8 * progress, Color::RED);
+        gizmos.line_2d(muzzle, muzzle + 10.0 * progress * direction, Color::RED);
+    }
+}
+
+/// Explosions that reach a destructible cannon take one hit point from it. Destroyed cannons explode
+/// too, which can take out cannons next to them.
+fn damage_cannons(
+    mut reader: Local<ManualEventReader<StartExplosion>>,
+    mut explosion_events: ResMut<Events<StartExplosion>>,
+    mut cannons_query: Query<(
+        Entity,
+        &mut CannonHitPoints,
+        &GlobalTransform,
+        &YoleckBelongsToLevel,
+    )>,
+    mut commands: Commands,
+) {
+    let explosions = reader
+        .read(&explosion_events)
+        .map(|explosion| (explosion.level, explosion.position, explosion.config.radius))
+        .collect::<Vec<_>>();
+    for (cannon_entity, mut hit_points, transform, belongs_to_level) in cannons_query.iter_mut() {
+        let position = transform.translation().truncate();
+        for (level, explosion_position, explosion_radius) in explosions.iter() {
+            if *level != belongs_to_level.level
+                || explosion_radius + CANNON_RADIUS < position.distance(*explosion_position)
+                || hit_points.0 == 0
+            {
+                continue;
+            }
+            hit_points.0 -= 1;
+            if hit_points.0 == 0 {
+                commands.entity(cannon_entity).despawn_recursive();
+                explosion_events.send(StartExplosion {
+                    level: belongs_to_level.level,
+                    position,
+                    config: CANNON_DESTRUCTION_EXPLOSION,
+                });
+            }
+        }
+    }
+}
+
+fn draw_cannon_hit_points(query: Query<(&CannonHitPoints, &GlobalTransform)>, mut gizmos: Gizmos) {
+    for (hit_points, transform) in query.iter() {
+        let first = transform.translation().truncate()
+            + Vec2::new(-0.25 * (hit_points.0 as f32 - 1.0), 1.5 * CANNON_RADIUS);
+        for i in 0..hit_points.0 {
+            gizmos.circle_2d(first + Vec2::new(0.5 * i as f32, 0.0), 0.15, Color::GREEN);
         }
     }
 }
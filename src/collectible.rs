@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_egui::{egui, EguiContexts};
+use bevy_pkv::PkvStore;
+use bevy_rapier2d::prelude::*;
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol_3d::Vpeol3dPosition;
+use bevy_yoleck::YoleckLevelJustLoaded;
+use serde::{Deserialize, Serialize};
+
+use crate::door::IsDoor;
+use crate::player::IsPlayer;
+use crate::utils::{collision_started_events_both_ways, CachedPbrMaker};
+use crate::During;
+
+/// Keys that unlock doors and optional gems, collected by touching them.
+pub struct CollectiblePlugin;
+
+impl Plugin for CollectiblePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelCollectibles>();
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("Key")
+                .with::<Vpeol3dPosition>()
+                .with::<KeyColor>()
+                .insert_on_init(|| IsKey)
+        });
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("Gem")
+                .with::<Vpeol3dPosition>()
+                .insert_on_init(|| IsGem)
+        });
+
+        app.add_yoleck_edit_system(edit_key_color);
+        app.add_yoleck_edit_system(edit_door_lock);
+
+        app.add_systems(YoleckSchedule::LevelLoaded, reset_level_collectibles);
+        app.add_systems(YoleckSchedule::Populate, (populate_key, populate_gem));
+        app.add_systems(
+            Update,
+            (pick_up_collectibles, show_collectibles_hud).in_set(During::Gameplay),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct IsKey;
+
+#[derive(Component)]
+pub struct IsGem;
+
+#[derive(
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Debug,
+    Serialize,
+    Deserialize,
+    Component,
+    YoleckComponent,
+)]
+pub enum KeyColor {
+    #[default]
+    Red,
+    Blue,
+    Green,
+    Yellow,
+}
+
+impl KeyColor {
+    pub const ALL: [KeyColor; 4] = [
+        KeyColor::Red,
+        KeyColor::Blue,
+        KeyColor::Green,
+        KeyColor::Yellow,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyColor::Red => "Red",
+            KeyColor::Blue => "Blue",
+            KeyColor::Green => "Green",
+            KeyColor::Yellow => "Yellow",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            KeyColor::Red => Color::RED,
+            KeyColor::Blue => Color::BLUE,
+            KeyColor::Green => Color::GREEN,
+            KeyColor::Yellow => Color::YELLOW,
+        }
+    }
+}
+
+/// What a door needs before the players can leave through it.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct DoorLock {
+    pub key: Option<KeyColor>,
+    /// Keep the door locked until all the destructible cannons in the level are destroyed.
+    #[serde(default)]
+    pub requires_destroying_cannons: bool,
+}
+
+/// What the players have collected in the level that is currently being played.
+#[derive(Resource, Default, Debug)]
+pub struct LevelCollectibles {
+    pub keys: HashSet<KeyColor>,
+    pub gems_collected: usize,
+    pub gems_total: usize,
+}
+
+/// The best gem count achieved in a level, as stored in the save data.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct GemsRecord {
+    pub collected: usize,
+    pub total: usize,
+}
+
+impl GemsRecord {
+    fn pkv_key(level: &str) -> String {
+        format!("gems/{}", level)
+    }
+
+    pub fn load(pkv: &PkvStore, level: &str) -> Option<Self> {
+        pkv.get(Self::pkv_key(level)).ok()
+    }
+
+    /// Save the gems collected in the level, unless a better result was already saved.
+    pub fn save_if_better(pkv: &mut PkvStore, level: &str, collectibles: &LevelCollectibles) {
+        if collectibles.gems_total == 0 {
+            return;
+        }
+        if let Some(existing) = Self::load(pkv, level) {
+            if collectibles.gems_collected <= existing.collected {
+                return;
+            }
+        }
+        let record = Self {
+            collected: collectibles.gems_collected,
+            total: collectibles.gems_total,
+        };
+        if let Err(err) = pkv.set(Self::pkv_key(level), &record) {
+            error!("Unable to save gems record: {}", err);
+        }
+    }
+}
+
+fn reset_level_collectibles(
+    levels_query: Query<Entity, With<YoleckLevelJustLoaded>>,
+    gems_query: Query<&YoleckBelongsToLevel, With<IsGem>>,
+    mut level_collectibles: ResMut<LevelCollectibles>,
+) {
+    for level_entity in levels_query.iter() {
+        *level_collectibles = LevelCollectibles {
+            gems_total: gems_query
+                .iter()
+                .filter(|belongs_to_level| belongs_to_level.level == level_entity)
+                .count(),
+            ..Default::default()
+        };
+    }
+}
+
+fn populate_key(
+    mut populate: YoleckPopulate<&KeyColor>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut mesh: Local<Option<Handle<Mesh>>>,
+    mut key_materials: Local<HashMap<KeyColor, Handle<StandardMaterial>>>,
+) {
+    populate.populate(|ctx, mut cmd, key_color| {
+        if ctx.is_first_time() {
+            cmd.insert(PbrBundle {
+                mesh: mesh
+                    .get_or_insert_with(|| meshes.add(Mesh::from(shape::Box::new(0.4, 0.8, 0.4))))
+                    .clone(),
+                ..Default::default()
+            });
+            cmd.insert(Collider::ball(0.6));
+            cmd.insert(Sensor);
+        }
+        cmd.insert(
+            key_materials
+                .entry(*key_color)
+                .or_insert_with(|| materials.add(key_color.color().into()))
+                .clone(),
+        );
+    });
+}
+
+fn populate_gem(mut populate: YoleckPopulate<(), With<IsGem>>, mut pbr: CachedPbrMaker) {
+    populate.populate(|ctx, mut cmd, ()| {
+        if ctx.is_first_time() {
+            cmd.insert(pbr.make_pbr_with(
+                || {
+                    Mesh::try_from(shape::Icosphere {
+                        radius: 0.4,
+                        subdivisions: 1,
+                    })
+                    .unwrap()
+                },
+                || Color::CYAN.into(),
+            ));
+            cmd.insert(Collider::ball(0.6));
+            cmd.insert(Sensor);
+        }
+    });
+}
+
+fn edit_key_color(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut KeyColor>) {
+    let Ok(mut key_color) = edit.get_single_mut() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Key Color:");
+        for option in KeyColor::ALL {
+            ui.selectable_value(key_color.as_mut(), option, option.name());
+        }
+    });
+}
+
+fn edit_door_lock(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut DoorLock, With<IsDoor>>) {
+    let Ok(mut door_lock) = edit.get_single_mut() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Requires Key:");
+        ui.selectable_value(&mut door_lock.key, None, "None");
+        for option in KeyColor::ALL {
+            ui.selectable_value(&mut door_lock.key, Some(option), option.name());
+        }
+    });
+    ui.checkbox(
+        &mut door_lock.requires_destroying_cannons,
+        "Requires Destroying All Cannons",
+    );
+}
+
+fn pick_up_collectibles(
+    mut reader: EventReader<CollisionEvent>,
+    players_query: Query<(), With<IsPlayer>>,
+    keys_query: Query<&KeyColor, With<IsKey>>,
+    gems_query: Query<(), With<IsGem>>,
+    mut level_collectibles: ResMut<LevelCollectibles>,
+    mut commands: Commands,
+) {
+    let mut picked_up = HashSet::new();
+    for (e1, e2) in collision_started_events_both_ways(&mut reader) {
+        if !players_query.contains(e1) || !picked_up.insert(e2) {
+            continue;
+        }
+        if let Ok(key_color) = keys_query.get(e2) {
+            level_collectibles.keys.insert(*key_color);
+        } else if gems_query.contains(e2) {
+            level_collectibles.gems_collected += 1;
+        } else {
+            continue;
+        }
+        commands.entity(e2).despawn_recursive();
+    }
+}
+
+fn show_collectibles_hud(
+    mut egui_contexts: EguiContexts,
+    level_collectibles: Res<LevelCollectibles>,
+) {
+    if level_collectibles.keys.is_empty() && level_collectibles.gems_total == 0 {
+        return;
+    }
+    egui::Area::new("collectibles-hud")
+        .anchor(egui::Align2::RIGHT_TOP, egui::Vec2::new(-10.0, 10.0))
+        .interactable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for key_color in KeyColor::ALL {
+                    if level_collectibles.keys.contains(&key_color) {
+                        let [r, g, b, _] = key_color.color().as_rgba_u8();
+                        ui.label(
+                            egui::RichText::new("🔑")
+                                .size(32.0)
+                                .color(egui::Color32::from_rgb(r, g, b)),
+                        );
+                    }
+                }
+                if 0 < level_collectibles.gems_total {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{}/{} gems",
+                            level_collectibles.gems_collected, level_collectibles.gems_total
+                        ))
+                        .size(32.0)
+                        .color(egui::Color32::LIGHT_BLUE),
+                    );
+                }
+            });
+        });
+}
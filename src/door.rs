@@ -1,12 +1,15 @@
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 use bevy_rapier2d::prelude::*;
 use bevy_yoleck::prelude::*;
 use bevy_yoleck::vpeol::VpeolWillContainClickableChildren;
 use bevy_yoleck::vpeol_3d::Vpeol3dPosition;
 
+use crate::cannon::CannonHitPoints;
+use crate::collectible::{DoorLock, LevelCollectibles};
+use crate::logic::{LogicInputs, LogicPowered};
 use crate::player::IsPlayer;
-use crate::utils::collision_started_events_both_ways;
-use crate::AppState;
+use crate::{AppState, During};
 
 pub struct DoorPlugin;
 
@@ -15,11 +18,16 @@ impl Plugin for DoorPlugin {
         app.add_yoleck_entity_type({
             YoleckEntityType::new("Door")
                 .with::<Vpeol3dPosition>()
+                .with::<LogicInputs>()
+                .with::<DoorLock>()
                 .insert_on_init(|| IsDoor)
         });
 
         app.add_systems(YoleckSchedule::Populate, populate_door);
-        app.add_systems(Update, player_enter_door);
+        app.add_systems(
+            Update,
+            (player_enter_door, mark_locked_doors).in_set(During::Gameplay),
+        );
     }
 }
 
@@ -40,15 +48,102 @@ fn populate_door(mut populate: YoleckPopulate<(), With<IsDoor>>, asset_server: R
     });
 }
 
+/// Players that reach the door leave the level. The level is completed once all of them are out.
+///
+/// Doors that are wired to switches or pressure plates stay locked while they are not powered, and
+/// doors that require a key stay locked until the players pick it up. Doors can also require
+/// destroying all the cannons that can be destroyed.
 fn player_enter_door(
-    mut reader: EventReader<CollisionEvent>,
-    player_query: Query<(), With<IsPlayer>>,
-    door_query: Query<(), With<IsDoor>>,
+    rapier_context: Res<RapierContext>,
+    player_query: Query<Entity, With<IsPlayer>>,
+    door_query: Query<
+        (
+            Entity,
+            Option<&LogicPowered>,
+            &DoorLock,
+            &YoleckBelongsToLevel,
+        ),
+        With<IsDoor>,
+    >,
+    cannons_query: Query<&YoleckBelongsToLevel, With<CannonHitPoints>>,
+    level_collectibles: Res<LevelCollectibles>,
+    mut commands: Commands,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
-    for (e1, e2) in collision_started_events_both_ways(&mut reader) {
-        if player_query.contains(e1) && door_query.contains(e2) {
-            next_state.set(AppState::LevelCompleted);
+    let mut players_that_exited = HashSet::new();
+    // Checking the players that are already inside every frame, so that a door that gets unlocked
+    // while a player stands in it lets them out right away.
+    for (door_entity, powered, door_lock, belongs_to_level) in door_query.iter() {
+        if powered.is_some_and(|powered| !powered.0)
+            || door_lock
+                .key
+                .is_some_and(|key| !level_collectibles.keys.contains(&key))
+            || (door_lock.requires_destroying_cannons
+                && cannons_remain(&cannons_query, belongs_to_level.level))
+        {
+            continue;
+        }
+        for (e1, e2, intersecting) in rapier_context.intersections_with(door_entity) {
+            if !intersecting {
+                continue;
+            }
+            let other = if e1 == door_entity { e2 } else { e1 };
+            if player_query.contains(other) {
+                players_that_exited.insert(other);
+            }
+        }
+    }
+    if players_that_exited.is_empty() {
+        return;
+    }
+    if player_query
+        .iter()
+        .all(|player_entity| players_that_exited.contains(&player_entity))
+    {
+        next_state.set(AppState::LevelCompleted);
+    } else {
+        for player_entity in players_that_exited {
+            commands.entity(player_entity).despawn_recursive();
+        }
+    }
+}
+
+fn cannons_remain(
+    cannons_query: &Query<&YoleckBelongsToLevel, With<CannonHitPoints>>,
+    level: Entity,
+) -> bool {
+    cannons_query
+        .iter()
+        .any(|belongs_to_level| belongs_to_level.level == level)
+}
+
+fn mark_locked_doors(
+    query: Query<
+        (
+            &GlobalTransform,
+            Option<&LogicPowered>,
+            &DoorLock,
+            &YoleckBelongsToLevel,
+        ),
+        With<IsDoor>,
+    >,
+    cannons_query: Query<&YoleckBelongsToLevel, With<CannonHitPoints>>,
+    level_collectibles: Res<LevelCollectibles>,
+    mut gizmos: Gizmos,
+) {
+    for (transform, powered, door_lock, belongs_to_level) in query.iter() {
+        if powered.is_some_and(|powered| !powered.0) {
+            gizmos.circle(transform.translation(), Vec3::Z, 2.5, Color::RED);
+        }
+        if let Some(key) = door_lock.key {
+            if !level_collectibles.keys.contains(&key) {
+                gizmos.circle(transform.translation(), Vec3::Z, 2.2, key.color());
+            }
+        }
+        if door_lock.requires_destroying_cannons
+            && cannons_remain(&cannons_query, belongs_to_level.level)
+        {
+            gizmos.circle(transform.translation(), Vec3::Z, 2.8, Color::ORANGE);
         }
     }
 }
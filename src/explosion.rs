@@ -10,11 +10,16 @@ pub struct ExplosionPlugin;
 impl Plugin for ExplosionPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<StartExplosion>();
+        app.init_resource::<ShowExplosionFields>();
         app.add_systems(Update, start_explosions);
         app.add_systems(
             Update,
             (progress_explosion_lifetime, apply_explosion_force).in_set(During::Gameplay),
         );
+        app.add_systems(
+            Update,
+            draw_explosion_fields.run_if(|show: Res<ShowExplosionFields>| show.0),
+        );
     }
 }
 
@@ -22,16 +27,72 @@ impl Plugin for ExplosionPlugin {
 pub struct StartExplosion {
     pub level: Entity,
     pub position: Vec2,
+    pub config: ExplosionConfig,
+}
+
+/// How the push of an explosion weakens with the distance from its center.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExplosionFalloff {
+    /// Drops evenly, down to nothing at the edge of the explosion.
+    Linear,
+    /// Proportional to `1 / distance`.
+    Inverse,
+    /// Proportional to `1 / distance²`.
+    InverseSquare,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ExplosionConfig {
+    /// How far the explosion reaches once it is fully grown.
+    pub radius: f32,
+    /// How long, in seconds, the explosion lasts.
+    pub duration: f32,
+    /// The push at a distance of `1.0` from the center - or, for [`ExplosionFalloff::Linear`], at
+    /// the center itself.
+    pub force: f32,
+    pub falloff: ExplosionFalloff,
+}
+
+impl Default for ExplosionConfig {
+    fn default() -> Self {
+        Self {
+            radius: 6.0,
+            duration: 0.3,
+            force: 1000.0,
+            falloff: ExplosionFalloff::Inverse,
+        }
+    }
+}
+
+/// The inverse falloffs get too strong right next to the center, so distances are not taken to be
+/// shorter than this.
+const MIN_FALLOFF_DISTANCE: f32 = 0.1;
+
+impl ExplosionConfig {
+    /// The acceleration the explosion applies to things `distance` away from its center.
+    pub fn force_at(&self, distance: f32) -> f32 {
+        let distance = distance.max(MIN_FALLOFF_DISTANCE);
+        match self.falloff {
+            ExplosionFalloff::Linear => self.force * (1.0 - distance / self.radius).max(0.0),
+            ExplosionFalloff::Inverse => self.force / distance,
+            ExplosionFalloff::InverseSquare => self.force / distance.powi(2),
+        }
+    }
 }
 
 #[derive(Component)]
 struct ExplosionStatus {
     timer: Timer,
+    config: ExplosionConfig,
 }
 
 #[derive(Component)]
 pub struct PushableByExplosion;
 
+/// Draws the force field of every explosion, for tuning [`ExplosionConfig`]s.
+#[derive(Resource, Default)]
+pub struct ShowExplosionFields(pub bool);
+
 fn start_explosions(
     mut reader: EventReader<StartExplosion>,
     mut commands: Commands,
@@ -97,7 +158,8 @@ fn start_explosions(
         cmd.insert(Sensor);
 
         cmd.insert(ExplosionStatus {
-            timer: Timer::from_seconds(0.30, TimerMode::Once),
+            timer: Timer::from_seconds(event.config.duration, TimerMode::Once),
+            config: event.config,
         });
     }
 }
@@ -112,18 +174,18 @@ fn progress_explosion_lifetime(
             commands.entity(entity).despawn_recursive();
         } else {
             let progress = status.timer.elapsed_secs() / status.timer.duration().as_secs_f32();
-            transform.scale = 6.0 * progress.powf(0.125) * Vec3::ONE;
+            transform.scale = status.config.radius * progress.powf(0.125) * Vec3::ONE;
         }
     }
 }
 
 fn apply_explosion_force(
     time: Res<Time>,
-    explosions_query: Query<(Entity, &GlobalTransform), With<ExplosionStatus>>,
+    explosions_query: Query<(Entity, &ExplosionStatus, &GlobalTransform)>,
     rapier_context: Res<RapierContext>,
     mut pushables_query: Query<(&GlobalTransform, &mut Velocity), With<PushableByExplosion>>,
 ) {
-    for (explosion_entity, explosion_transform) in explosions_query.iter() {
+    for (explosion_entity, status, explosion_transform) in explosions_query.iter() {
         for (e1, e2, intersecting) in rapier_context.intersections_with(explosion_entity) {
             if !intersecting {
                 continue;
@@ -136,12 +198,44 @@ fn apply_explosion_force(
             };
             let push_vector =
                 (pushable_transform.translation() - explosion_transform.translation()).truncate();
-            if push_vector.length_squared() == 0.0 {
+            let Some(push_direction) = push_vector.try_normalize() else {
                 continue;
-            }
-            let force_vector =
-                1000.0 * (push_vector / push_vector.length_squared()).clamp_length_max(10.0);
+            };
+            let force_vector = status.config.force_at(push_vector.length()) * push_direction;
             pushable_velocity.linvel += time.delta_seconds() * force_vector;
         }
     }
 }
+
+/// Rings whose brightness shows the force at their distance, and the push on everything inside.
+fn draw_explosion_fields(
+    explosions_query: Query<(&ExplosionStatus, &GlobalTransform)>,
+    pushables_query: Query<&GlobalTransform, With<PushableByExplosion>>,
+    mut gizmos: Gizmos,
+) {
+    const RINGS: usize = 8;
+    for (status, transform) in explosions_query.iter() {
+        let center = transform.translation().truncate();
+        let config = &status.config;
+        let strongest = config.force_at(config.radius / RINGS as f32);
+        for ring in 1..=RINGS {
+            let distance = config.radius * ring as f32 / RINGS as f32;
+            let intensity = (config.force_at(distance) / strongest).clamp(0.0, 1.0);
+            gizmos.circle_2d(center, distance, Color::rgb(1.0, intensity, 0.0));
+        }
+        for pushable_transform in pushables_query.iter() {
+            let push_vector = pushable_transform.translation().truncate() - center;
+            let distance = push_vector.length();
+            if config.radius < distance {
+                continue;
+            }
+            let start = pushable_transform.translation().truncate();
+            let length = 2.0 * config.force_at(distance) / strongest;
+            gizmos.line_2d(
+                start,
+                start + length * push_vector.normalize_or_zero(),
+                Color::CYAN,
+            );
+        }
+    }
+}
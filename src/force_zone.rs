@@ -0,0 +1,279 @@
+use std::mem::{discriminant, Discriminant};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::egui;
+use bevy_rapier2d::prelude::*;
+use bevy_tnua::prelude::*;
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dScale};
+use serde::{Deserialize, Serialize};
+
+use crate::missile::MissileConfig;
+use crate::player::IsPlayer;
+use crate::utils::{keep_zone_behind_geometry, resize_with_corner_knobs};
+use crate::During;
+
+pub struct ForceZonePlugin;
+
+impl Plugin for ForceZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("ForceZone")
+                .with::<Vpeol3dPosition>()
+                .with::<Vpeol3dScale>()
+                .with::<ForceZone>()
+        });
+
+        app.add_yoleck_edit_system(resize_with_corner_knobs::<With<ForceZone>>);
+        app.add_yoleck_edit_system(keep_zone_behind_geometry::<With<ForceZone>>);
+        app.add_yoleck_edit_system(edit_force_zone);
+
+        app.add_systems(YoleckSchedule::Populate, populate_force_zone);
+        app.add_systems(Update, draw_wind_directions);
+        app.add_systems(
+            Update,
+            (
+                update_zone_effects.before(TnuaUserControlsSystemSet),
+                apply_zone_effects.after(update_zone_effects),
+            )
+                .in_set(During::Gameplay),
+        );
+    }
+}
+
+/// A rectangle that affects the players and the missiles inside it.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub enum ForceZone {
+    /// Pushes things in the direction of `velocity`, harder the faster it is.
+    Wind { velocity: Vec2 },
+    /// Scales the gravity the players feel. Negative values flip the players upside down.
+    Gravity { scale: f32 },
+    /// Missiles inside the zone stop homing in on the players.
+    Jamming,
+}
+
+impl Default for ForceZone {
+    fn default() -> Self {
+        Self::Wind {
+            velocity: Vec2::new(10.0, 0.0),
+        }
+    }
+}
+
+impl ForceZone {
+    const ALL: [ForceZone; 3] = [
+        ForceZone::Wind {
+            velocity: Vec2::new(10.0, 0.0),
+        },
+        ForceZone::Gravity { scale: 0.5 },
+        ForceZone::Jamming,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ForceZone::Wind { .. } => "Wind",
+            ForceZone::Gravity { .. } => "Gravity",
+            ForceZone::Jamming => "Jamming",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            ForceZone::Wind { .. } => Color::rgba(0.8, 0.8, 1.0, 0.15),
+            ForceZone::Gravity { .. } => Color::rgba(0.6, 0.2, 1.0, 0.15),
+            ForceZone::Jamming => Color::rgba(1.0, 0.3, 0.1, 0.15),
+        }
+    }
+}
+
+/// The combined effect of all the force zones an entity is inside.
+#[derive(Component, Debug)]
+pub struct ZoneEffects {
+    pub wind: Vec2,
+    pub gravity_scale: f32,
+    pub jammed: bool,
+}
+
+impl Default for ZoneEffects {
+    fn default() -> Self {
+        Self {
+            wind: Vec2::ZERO,
+            gravity_scale: 1.0,
+            jammed: false,
+        }
+    }
+}
+
+impl ZoneEffects {
+    /// The direction the player stands in, based on the gravity they feel.
+    pub fn up(&self) -> Vec3 {
+        if self.gravity_scale < 0.0 {
+            Vec3::NEG_Y
+        } else {
+            Vec3::Y
+        }
+    }
+}
+
+/// The acceleration the wind applies, per unit of its velocity.
+const WIND_RESPONSE: f32 = 4.0;
+
+fn populate_force_zone(
+    mut populate: YoleckPopulate<&ForceZone>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut mesh: Local<Option<Handle<Mesh>>>,
+    mut zone_materials: Local<HashMap<Discriminant<ForceZone>, Handle<StandardMaterial>>>,
+) {
+    populate.populate(|ctx, mut cmd, force_zone| {
+        if ctx.is_first_time() {
+            cmd.insert(PbrBundle {
+                mesh: mesh
+                    .get_or_insert_with(|| meshes.add(Mesh::from(shape::Box::new(1.0, 1.0, 0.01))))
+                    .clone(),
+                ..Default::default()
+            });
+        }
+        cmd.insert(
+            zone_materials
+                .entry(discriminant(force_zone))
+                .or_insert_with(|| {
+                    materials.add(StandardMaterial {
+                        base_color: force_zone.color(),
+                        alpha_mode: AlphaMode::Blend,
+                        unlit: true,
+                        ..Default::default()
+                    })
+                })
+                .clone(),
+        );
+    });
+}
+
+fn edit_force_zone(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut ForceZone>) {
+    let Ok(mut force_zone) = edit.get_single_mut() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Effect:");
+        for option in ForceZone::ALL {
+            let is_selected = discriminant(force_zone.as_ref()) == discriminant(&option);
+            if ui.selectable_label(is_selected, option.name()).clicked() && !is_selected {
+                *force_zone = option;
+            }
+        }
+    });
+    match force_zone.as_mut() {
+        ForceZone::Wind { velocity } => {
+            ui.add(egui::Slider::new(&mut velocity.x, -50.0..=50.0).prefix("Wind X: "));
+            ui.add(egui::Slider::new(&mut velocity.y, -50.0..=50.0).prefix("Wind Y: "));
+        }
+        ForceZone::Gravity { scale } => {
+            ui.add(egui::Slider::new(scale, -1.0..=2.0).prefix("Gravity Scale: "));
+        }
+        ForceZone::Jamming => {}
+    }
+}
+
+fn draw_wind_directions(query: Query<(&ForceZone, &GlobalTransform)>, mut gizmos: Gizmos) {
+    for (force_zone, transform) in query.iter() {
+        let ForceZone::Wind { velocity } = force_zone else {
+            continue;
+        };
+        let (scale, _, translation) = transform.to_scale_rotation_translation();
+        let half_arrow = 0.25 * velocity.normalize_or_zero() * scale.truncate().min_element();
+        let start = translation.truncate() - half_arrow;
+        let end = translation.truncate() + half_arrow;
+        gizmos.line_2d(start, end, Color::WHITE);
+        for side in [1.0, -1.0] {
+            gizmos.line_2d(
+                end,
+                end - 0.3 * half_arrow + 0.2 * side * half_arrow.perp(),
+                Color::WHITE,
+            );
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_zone_effects(
+    zones_query: Query<(&ForceZone, &GlobalTransform)>,
+    mut affected_query: Query<
+        (Entity, &GlobalTransform, Option<&mut ZoneEffects>),
+        Or<(With<IsPlayer>, With<MissileConfig>)>,
+    >,
+    mut commands: Commands,
+) {
+    let zones = zones_query
+        .iter()
+        .map(|(force_zone, transform)| {
+            let (scale, _, translation) = transform.to_scale_rotation_translation();
+            (
+                force_zone,
+                Rect::from_center_size(translation.truncate(), scale.truncate()),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (entity, transform, zone_effects) in affected_query.iter_mut() {
+        let position = transform.translation().truncate();
+        let mut new_effects = ZoneEffects::default();
+        for (force_zone, rect) in zones.iter() {
+            if !rect.contains(position) {
+                continue;
+            }
+            match force_zone {
+                ForceZone::Wind { velocity } => {
+                    new_effects.wind += *velocity;
+                }
+                ForceZone::Gravity { scale } => {
+                    new_effects.gravity_scale *= scale;
+                }
+                ForceZone::Jamming => {
+                    new_effects.jammed = true;
+                }
+            }
+        }
+        if let Some(mut zone_effects) = zone_effects {
+            *zone_effects = new_effects;
+        } else {
+            commands.entity(entity).insert(new_effects);
+        }
+    }
+}
+
+/// Players get their horizontal wind through `apply_controls`, so that it won't fight Tnua.
+#[allow(clippy::type_complexity)]
+fn apply_zone_effects(
+    time: Res<Time>,
+    mut players_query: Query<
+        (
+            &ZoneEffects,
+            &mut Velocity,
+            Option<&mut GravityScale>,
+            Entity,
+        ),
+        With<IsPlayer>,
+    >,
+    mut missiles_query: Query<
+        (&ZoneEffects, &mut Velocity),
+        (With<MissileConfig>, Without<IsPlayer>),
+    >,
+    mut commands: Commands,
+) {
+    for (zone_effects, mut velocity, gravity_scale, entity) in players_query.iter_mut() {
+        velocity.linvel.y += WIND_RESPONSE * zone_effects.wind.y * time.delta_seconds();
+        if let Some(mut gravity_scale) = gravity_scale {
+            if gravity_scale.0 != zone_effects.gravity_scale {
+                gravity_scale.0 = zone_effects.gravity_scale;
+            }
+        } else {
+            commands
+                .entity(entity)
+                .insert(GravityScale(zone_effects.gravity_scale));
+        }
+    }
+    for (zone_effects, mut velocity) in missiles_query.iter_mut() {
+        velocity.linvel += WIND_RESPONSE * zone_effects.wind * time.delta_seconds();
+    }
+}
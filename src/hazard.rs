@@ -0,0 +1,316 @@
+use std::mem::{discriminant, Discriminant};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::egui;
+use bevy_rapier2d::prelude::*;
+use bevy_tnua::TnuaProximitySensor;
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dScale};
+use serde::{Deserialize, Serialize};
+
+use crate::player::IsPlayer;
+use crate::player_controls::FLOAT_HEIGHT;
+use crate::utils::{resize_with_corner_knobs, CachedPbrMaker};
+use crate::{AppState, During};
+
+/// Things that kill the players when they touch them, and the height below which they die.
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("Hazard")
+                .with::<Vpeol3dPosition>()
+                .with::<Vpeol3dScale>()
+                .with::<Hazard>()
+        });
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("KillPlane")
+                .with::<Vpeol3dPosition>()
+                .insert_on_init(|| IsKillPlane)
+        });
+
+        app.add_yoleck_edit_system(resize_with_corner_knobs::<With<Hazard>>);
+        app.add_yoleck_edit_system(edit_hazard);
+
+        app.add_systems(
+            YoleckSchedule::Populate,
+            (populate_hazard, populate_kill_plane),
+        );
+        app.add_systems(Update, draw_kill_planes.run_if(in_state(AppState::Editor)));
+        app.add_systems(
+            Update,
+            (
+                (cycle_lasers, spin_saw_blades),
+                (kill_players_touching_hazards, kill_players_below_kill_plane),
+            )
+                .chain()
+                .in_set(During::Gameplay),
+        );
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub enum Hazard {
+    #[default]
+    Spikes,
+    Lava,
+    SawBlade,
+    /// A beam that turns on and off in a cycle.
+    Laser {
+        on_seconds: f32,
+        off_seconds: f32,
+    },
+}
+
+impl Hazard {
+    const ALL: [Hazard; 4] = [
+        Hazard::Spikes,
+        Hazard::Lava,
+        Hazard::SawBlade,
+        Hazard::Laser {
+            on_seconds: 1.0,
+            off_seconds: 1.0,
+        },
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Hazard::Spikes => "Spikes",
+            Hazard::Lava => "Lava",
+            Hazard::SawBlade => "Saw Blade",
+            Hazard::Laser { .. } => "Laser",
+        }
+    }
+
+    fn mesh(&self) -> Mesh {
+        match self {
+            Hazard::Spikes | Hazard::Lava | Hazard::Laser { .. } => {
+                Mesh::from(shape::Box::new(1.0, 1.0, 1.0))
+            }
+            Hazard::SawBlade => Mesh::from(shape::RegularPolygon::new(0.5, 8)),
+        }
+    }
+
+    fn material(&self) -> StandardMaterial {
+        match self {
+            Hazard::Spikes => Color::GRAY.into(),
+            Hazard::Lava => StandardMaterial {
+                base_color: Color::ORANGE_RED,
+                emissive: Color::ORANGE,
+                ..Default::default()
+            },
+            Hazard::SawBlade => StandardMaterial {
+                base_color: Color::SILVER,
+                double_sided: true,
+                cull_mode: None,
+                ..Default::default()
+            },
+            Hazard::Laser { .. } => StandardMaterial {
+                base_color: Color::rgba(1.0, 0.0, 0.0, 0.7),
+                emissive: Color::RED,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn collider(&self) -> Collider {
+        match self {
+            Hazard::Spikes | Hazard::Lava | Hazard::Laser { .. } => Collider::cuboid(0.5, 0.5),
+            Hazard::SawBlade => Collider::ball(0.5),
+        }
+    }
+}
+
+/// Whether something kills the players touching it - either a [`Hazard`] or a block with a hazard
+/// surface. Only lasers ever turn off.
+#[derive(Component)]
+pub struct HazardActive(pub bool);
+
+/// How far into its on-off cycle a laser is.
+#[derive(Component)]
+struct LaserCycle(f32);
+
+/// Marks the height below which the players die. Every level needs one - level validation reports
+/// levels without it.
+#[derive(Component)]
+pub struct IsKillPlane;
+
+/// The height below which the players die. The highest kill plane is the one that counts.
+pub fn kill_height(kill_planes: impl Iterator<Item = f32>) -> Option<f32> {
+    kill_planes.reduce(f32::max)
+}
+
+const SAW_BLADE_ANGULAR_SPEED: f32 = 10.0;
+/// Solid hazards stop the players right at their surface, so the players are checked with a
+/// slightly bigger shape to notice them.
+const HAZARD_TOUCH_MARGIN: f32 = 0.05;
+const KILL_PLANE_COLOR: Color = Color::RED;
+/// How far to the sides the kill plane is drawn in the editor.
+const KILL_PLANE_DRAW_EXTENT: f32 = 1000.0;
+
+#[allow(clippy::type_complexity)]
+fn populate_hazard(
+    mut populate: YoleckPopulate<&Hazard>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut hazard_assets: Local<
+        HashMap<Discriminant<Hazard>, (Handle<Mesh>, Handle<StandardMaterial>)>,
+    >,
+) {
+    populate.populate(|ctx, mut cmd, hazard| {
+        let (mesh, material) = hazard_assets
+            .entry(discriminant(hazard))
+            .or_insert_with(|| (meshes.add(hazard.mesh()), materials.add(hazard.material())))
+            .clone();
+        cmd.insert(PbrBundle {
+            mesh,
+            material,
+            ..Default::default()
+        });
+        cmd.insert(hazard.collider());
+        cmd.insert(Sensor);
+        cmd.insert(HazardActive(true));
+        if ctx.is_first_time() && !ctx.is_in_editor() {
+            cmd.insert(LaserCycle(0.0));
+        }
+    });
+}
+
+fn populate_kill_plane(
+    mut populate: YoleckPopulate<(), With<IsKillPlane>>,
+    mut pbr: CachedPbrMaker,
+) {
+    populate.populate(|ctx, mut cmd, ()| {
+        if ctx.is_first_time() {
+            cmd.insert(pbr.make_pbr_with(
+                || Mesh::from(shape::Box::new(2.0, 0.2, 0.2)),
+                || KILL_PLANE_COLOR.into(),
+            ));
+        }
+        if !ctx.is_in_editor() {
+            cmd.insert(Visibility::Hidden);
+        }
+    });
+}
+
+fn edit_hazard(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut Hazard>) {
+    let Ok(mut hazard) = edit.get_single_mut() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Hazard:");
+        for option in Hazard::ALL {
+            let is_selected = discriminant(hazard.as_ref()) == discriminant(&option);
+            if ui.selectable_label(is_selected, option.name()).clicked() && !is_selected {
+                *hazard = option;
+            }
+        }
+    });
+    if let Hazard::Laser {
+        on_seconds,
+        off_seconds,
+    } = hazard.as_mut()
+    {
+        ui.add(egui::Slider::new(on_seconds, 0.1..=10.0).prefix("On Seconds: "));
+        ui.add(egui::Slider::new(off_seconds, 0.0..=10.0).prefix("Off Seconds: "));
+    }
+}
+
+fn draw_kill_planes(query: Query<&GlobalTransform, With<IsKillPlane>>, mut gizmos: Gizmos) {
+    for transform in query.iter() {
+        let height = transform.translation().y;
+        gizmos.line_2d(
+            Vec2::new(-KILL_PLANE_DRAW_EXTENT, height),
+            Vec2::new(KILL_PLANE_DRAW_EXTENT, height),
+            KILL_PLANE_COLOR,
+        );
+    }
+}
+
+fn cycle_lasers(
+    time: Res<Time>,
+    mut query: Query<(&Hazard, &mut LaserCycle, &mut HazardActive, &mut Visibility)>,
+) {
+    for (hazard, mut laser_cycle, mut active, mut visibility) in query.iter_mut() {
+        let Hazard::Laser {
+            on_seconds,
+            off_seconds,
+        } = *hazard
+        else {
+            continue;
+        };
+        laser_cycle.0 = (laser_cycle.0 + time.delta_seconds()) % (on_seconds + off_seconds);
+        let is_on = laser_cycle.0 < on_seconds;
+        if active.0 != is_on {
+            active.0 = is_on;
+            *visibility = if is_on {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
+
+fn spin_saw_blades(time: Res<Time>, mut query: Query<(&Hazard, &mut Transform)>) {
+    for (hazard, mut transform) in query.iter_mut() {
+        if *hazard == Hazard::SawBlade {
+            transform.rotate_z(-SAW_BLADE_ANGULAR_SPEED * time.delta_seconds());
+        }
+    }
+}
+
+fn kill_players_touching_hazards(
+    rapier_context: Res<RapierContext>,
+    players_query: Query<(&GlobalTransform, &TnuaProximitySensor), With<IsPlayer>>,
+    hazards_query: Query<&HazardActive>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let is_active_hazard = |entity| hazards_query.get(entity).is_ok_and(|active| active.0);
+    for (player_transform, sensor) in players_query.iter() {
+        // Players float above the ground, so the space under them counts too - otherwise they
+        // could walk over spikes without ever touching them.
+        let top = player_transform.translation().y + 0.5 + HAZARD_TOUCH_MARGIN;
+        let bottom = player_transform.translation().y - FLOAT_HEIGHT;
+        let mut touched_hazard = sensor
+            .output
+            .as_ref()
+            .is_some_and(|ground| is_active_hazard(ground.entity));
+        rapier_context.intersections_with_shape(
+            Vec2::new(player_transform.translation().x, 0.5 * (top + bottom)),
+            0.0,
+            &Collider::cuboid(0.25 + HAZARD_TOUCH_MARGIN, 0.5 * (top - bottom)),
+            QueryFilter::new().predicate(&is_active_hazard),
+            |_| {
+                touched_hazard = true;
+                false
+            },
+        );
+        if touched_hazard {
+            app_state.set(AppState::GameOver);
+        }
+    }
+}
+
+fn kill_players_below_kill_plane(
+    kill_planes_query: Query<&GlobalTransform, With<IsKillPlane>>,
+    players_query: Query<&GlobalTransform, With<IsPlayer>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let Some(kill_height) = kill_height(
+        kill_planes_query
+            .iter()
+            .map(|transform| transform.translation().y),
+    ) else {
+        return;
+    };
+    for player_transform in players_query.iter() {
+        if player_transform.translation().y < kill_height {
+            app_state.set(AppState::GameOver);
+        }
+    }
+}
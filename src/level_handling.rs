@@ -4,6 +4,7 @@ use bevy_egui_kbgp::KbgpEguiUiCtxExt;
 use bevy_pkv::PkvStore;
 use bevy_yoleck::prelude::*;
 
+use crate::collectible::{GemsRecord, LevelCollectibles};
 use crate::menu::FocusLabel;
 use crate::AppState;
 
@@ -92,12 +93,14 @@ fn handle_level_completion(
     mut next_state: ResMut<NextState<AppState>>,
     level_index_assets: Res<Assets<YoleckLevelIndex>>,
     mut pkv: ResMut<PkvStore>,
+    level_collectibles: Res<LevelCollectibles>,
     mut egui_contexts: EguiContexts,
 ) {
     let finished_level_name = level_progress
         .current_level
         .take()
         .expect("current_level should be set when entering the LevelCompleted state");
+    GemsRecord::save_if_better(&mut pkv, &finished_level_name, &level_collectibles);
     if let Some(level_index) = level_index_assets.get(&level_progress.level_index) {
         let index_of_finished_level = level_index.iter().enumerate().find_map(|(index, level)| {
             if level.filename == finished_level_name {
@@ -0,0 +1,745 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet, Uuid};
+use bevy_egui::egui;
+use bevy_rapier2d::plugin::RapierConfiguration;
+use bevy_tnua::prelude::*;
+use bevy_yoleck::level_files_upgrading::upgrade_level_file;
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dRotatation, Vpeol3dScale};
+use bevy_yoleck::YoleckEditorSections;
+
+use crate::arena::{IsBlock, OneWayPlatform};
+use crate::cannon::IsCannon;
+use crate::collectible::{DoorLock, IsKey, KeyColor};
+use crate::door::IsDoor;
+use crate::hazard::{kill_height, IsKillPlane};
+use crate::logic::IsGate;
+use crate::player::{IsPlayer, PlayerSlot};
+use crate::player_controls::{
+    AIR_JUMP_HEIGHT, DASH_DISTANCE, FLOAT_HEIGHT, JUMP_HEIGHT, RUN_SPEED,
+};
+use crate::teleporter::{Teleporter, TELEPORTER_RADIUS};
+
+/// Adds a section to the editor for checking the edited level for problems.
+pub struct LevelValidationPlugin;
+
+impl Plugin for LevelValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelValidationReport>();
+        app.world
+            .resource_mut::<YoleckEditorSections>()
+            .0
+            .push(level_validation_section.into());
+    }
+}
+
+#[derive(Debug)]
+pub struct LevelProblem {
+    pub position: Option<Vec2>,
+    pub description: String,
+}
+
+impl fmt::Display for LevelProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(position) = self.position {
+            write!(
+                f,
+                "{} at ({:.1}, {:.1})",
+                self.description, position.x, position.y
+            )
+        } else {
+            write!(f, "{}", self.description)
+        }
+    }
+}
+
+impl LevelProblem {
+    fn new(position: impl Into<Option<Vec2>>, description: impl Into<String>) -> Self {
+        Self {
+            position: position.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// The parts of a level that matter for validating it.
+#[derive(Default)]
+struct LevelLayout {
+    players: Vec<(usize, Vec2)>,
+    doors: Vec<Vec2>,
+    /// Doors that need a key, and the key they need.
+    locked_doors: Vec<(Vec2, KeyColor)>,
+    keys: HashSet<KeyColor>,
+    /// Teleporter entrances and the positions of their destinations.
+    teleports: Vec<(Vec2, Vec2)>,
+    cannons: Vec<Vec2>,
+    blocks: Vec<LayoutBlock>,
+    kill_planes: Vec<f32>,
+}
+
+struct LayoutBlock {
+    center: Vec2,
+    half_size: Vec2,
+    /// The direction of the block's local X axis.
+    axis: Vec2,
+    /// One-way platforms only block movement from above.
+    one_way: bool,
+}
+
+impl LayoutBlock {
+    fn new(position: Vec3, scale: Vec3, rotation: Quat, one_way: bool) -> Self {
+        Self {
+            center: position.truncate(),
+            half_size: 0.5 * scale.truncate().abs(),
+            axis: (rotation * Vec3::X)
+                .truncate()
+                .try_normalize()
+                .unwrap_or(Vec2::X),
+            one_way,
+        }
+    }
+
+    fn overlaps_box(&self, center: Vec2, half_size: Vec2) -> bool {
+        let offset = center - self.center;
+        let perp = self.axis.perp();
+        [Vec2::X, Vec2::Y, self.axis, perp].into_iter().all(|n| {
+            let box_radius = half_size.x * n.x.abs() + half_size.y * n.y.abs();
+            let block_radius =
+                self.half_size.x * self.axis.dot(n).abs() + self.half_size.y * perp.dot(n).abs();
+            offset.dot(n).abs() < box_radius + block_radius
+        })
+    }
+}
+
+/// Validate all the levels listed in the `index.yoli` file inside `levels_directory`.
+pub fn validate_level_files(levels_directory: &Path) -> Vec<(String, Vec<LevelProblem>)> {
+    let index_path = levels_directory.join("index.yoli");
+    let level_index = match std::fs::read(&index_path)
+        .map_err(|err| err.to_string())
+        .and_then(|data| {
+            serde_json::from_slice::<YoleckLevelIndex>(&data).map_err(|err| err.to_string())
+        }) {
+        Ok(level_index) => level_index,
+        Err(err) => {
+            return vec![(
+                index_path.display().to_string(),
+                vec![LevelProblem::new(None, format!("Unable to read: {}", err))],
+            )];
+        }
+    };
+    level_index
+        .iter()
+        .map(|entry| {
+            let problems = match LevelLayout::from_file(&levels_directory.join(&entry.filename)) {
+                Ok(layout) => layout.validate(),
+                Err(err) => vec![LevelProblem::new(None, format!("Unable to read: {}", err))],
+            };
+            (entry.filename.clone(), problems)
+        })
+        .collect()
+}
+
+/// Match teleporters, given as `(uuid, position, destination)`, with their destinations' positions.
+fn resolve_teleports(teleporters: &[(Option<Uuid>, Vec2, Option<Uuid>)]) -> Vec<(Vec2, Vec2)> {
+    teleporters
+        .iter()
+        .filter_map(|(_, entrance, destination)| {
+            let destination = (*destination)?;
+            let (_, exit, _) = teleporters
+                .iter()
+                .find(|(uuid, _, _)| *uuid == Some(destination))?;
+            Some((*entrance, *exit))
+        })
+        .collect()
+}
+
+impl LevelLayout {
+    fn from_file(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|err| err.to_string())?;
+        let level = serde_json::from_slice(&data).map_err(|err| err.to_string())?;
+        let level = upgrade_level_file(level).map_err(|err| err.to_string())?;
+        let level: YoleckRawLevel = serde_json::from_value(level).map_err(|err| err.to_string())?;
+
+        fn component<T: Default + serde::de::DeserializeOwned>(
+            data: &serde_json::Value,
+            name: &str,
+        ) -> Result<T, String> {
+            match data.get(name) {
+                Some(value) => serde_json::from_value(value.clone()).map_err(|err| err.to_string()),
+                None => Ok(T::default()),
+            }
+        }
+
+        let mut layout = Self::default();
+        let mut teleporters = Vec::new();
+        for entry in level.entries() {
+            let position = component::<Vpeol3dPosition>(&entry.data, "Vpeol3dPosition")?.0;
+            match entry.header.type_name.as_str() {
+                "Player" => {
+                    let slot = component::<PlayerSlot>(&entry.data, "PlayerSlot")?;
+                    layout.players.push((slot.index, position.truncate()));
+                }
+                "Door" => {
+                    layout.doors.push(position.truncate());
+                    if let Some(key) = component::<DoorLock>(&entry.data, "DoorLock")?.key {
+                        layout.locked_doors.push((position.truncate(), key));
+                    }
+                }
+                "Key" => {
+                    layout
+                        .keys
+                        .insert(component::<KeyColor>(&entry.data, "KeyColor")?);
+                }
+                "Cannon" => layout.cannons.push(position.truncate()),
+                "KillPlane" => layout.kill_planes.push(position.y),
+                "Teleporter" => teleporters.push((
+                    entry.header.uuid,
+                    position.truncate(),
+                    component::<Teleporter>(&entry.data, "Teleporter")?.destination,
+                )),
+                // Moving blocks are checked at their starting position.
+                type_name @ ("Block" | "MovingBlock" | "OneWayPlatform") => {
+                    layout.blocks.push(LayoutBlock::new(
+                        position,
+                        component::<Vpeol3dScale>(&entry.data, "Vpeol3dScale")?.0,
+                        component::<Vpeol3dRotatation>(&entry.data, "Vpeol3dRotatation")?.0,
+                        type_name == "OneWayPlatform",
+                    ))
+                }
+                _ => {}
+            }
+        }
+        layout.teleports = resolve_teleports(&teleporters);
+        Ok(layout)
+    }
+
+    fn validate(&self) -> Vec<LevelProblem> {
+        let mut problems = Vec::new();
+
+        let mut players_by_slot = HashMap::<usize, Vec<Vec2>>::new();
+        for (slot, position) in self.players.iter() {
+            players_by_slot.entry(*slot).or_default().push(*position);
+        }
+        if !players_by_slot.contains_key(&0) {
+            problems.push(LevelProblem::new(None, "No Player"));
+        }
+        let mut slots = players_by_slot.keys().copied().collect::<Vec<_>>();
+        slots.sort();
+        for slot in slots.iter() {
+            for position in players_by_slot[slot].iter().skip(1) {
+                problems.push(LevelProblem::new(
+                    *position,
+                    format!("More than one Player in slot {}", slot + 1),
+                ));
+            }
+        }
+
+        if self.doors.is_empty() {
+            problems.push(LevelProblem::new(None, "No Door"));
+        }
+
+        if self.kill_planes.is_empty() {
+            problems.push(LevelProblem::new(None, "No KillPlane"));
+        }
+
+        for (door_position, key) in self.locked_doors.iter() {
+            if !self.keys.contains(key) {
+                problems.push(LevelProblem::new(
+                    *door_position,
+                    format!("Door requires a {} Key, but there is none", key.name()),
+                ));
+            }
+        }
+
+        for cannon_position in self.cannons.iter() {
+            if self
+                .blocks
+                .iter()
+                .any(|block| block.overlaps_box(*cannon_position, Vec2::ZERO))
+            {
+                problems.push(LevelProblem::new(
+                    *cannon_position,
+                    "Cannon is embedded inside a Block",
+                ));
+            }
+        }
+
+        if !self.doors.is_empty() {
+            for slot in slots.iter() {
+                let position = players_by_slot[slot][0];
+                if !ReachabilityCheck::new(self).can_reach_door_from(position) {
+                    problems.push(LevelProblem::new(
+                        position,
+                        format!("No Door is reachable from the Player in slot {}", slot + 1),
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+/// The player's capsule extends this much above its center.
+const PLAYER_TOP: f32 = 0.5;
+const PLAYER_HALF_WIDTH: f32 = 0.25;
+
+/// Sampled horizontal speeds for the jumps, as fractions of [`RUN_SPEED`].
+const SAMPLED_SPEED_RATIOS: [f32; 7] = [-1.0, -0.5, -0.25, 0.0, 0.25, 0.5, 1.0];
+
+const SIMULATION_TIME_STEP: f32 = 1.0 / 60.0;
+const SWEEP_STEP: f32 = 0.1;
+const WALK_STEP: f32 = 0.25;
+/// How high a step (or slope) the player can walk over.
+const MAX_STEP_UP: f32 = 0.3;
+const MAX_FLIGHT_TIME: f32 = 5.0;
+
+/// An approximation of where the player can get with the moves from `apply_controls`. It is
+/// deliberately generous - it should catch doors that are clearly out of reach without flagging
+/// jumps that are merely hard.
+struct ReachabilityCheck<'a> {
+    layout: &'a LevelLayout,
+    gravity: f32,
+    fall_gravity: f32,
+    kill_height: f32,
+    reached_door: bool,
+    used_teleports: HashSet<usize>,
+    /// Destinations of teleporters that were entered, but not explored from yet.
+    pending_teleport_exits: Vec<Vec2>,
+}
+
+/// The moves the player does while in the air, each done at the top of the previous one.
+#[derive(Clone, Copy)]
+struct Flight {
+    speed: f32,
+    jump_height: f32,
+    air_jump: bool,
+    dash: bool,
+}
+
+impl Flight {
+    /// Just falling down, without any movement.
+    const FALL: Flight = Flight {
+        speed: 0.0,
+        jump_height: 0.0,
+        air_jump: false,
+        dash: false,
+    };
+}
+
+impl<'a> ReachabilityCheck<'a> {
+    fn new(layout: &'a LevelLayout) -> Self {
+        let gravity = -RapierConfiguration::default().gravity.y;
+        Self {
+            layout,
+            gravity,
+            fall_gravity: gravity + TnuaBuiltinJump::default().fall_extra_gravity,
+            kill_height: kill_height(layout.kill_planes.iter().copied())
+                .unwrap_or(f32::NEG_INFINITY),
+            reached_door: false,
+            used_teleports: HashSet::new(),
+            pending_teleport_exits: Vec::new(),
+        }
+    }
+
+    /// The player's collision box, including the space under it that it floats over.
+    fn body(position: Vec2) -> (Vec2, Vec2) {
+        let bottom = position.y - FLOAT_HEIGHT;
+        let top = position.y + PLAYER_TOP;
+        (
+            Vec2::new(position.x, 0.5 * (bottom + top)),
+            Vec2::new(PLAYER_HALF_WIDTH, 0.5 * (top - bottom)),
+        )
+    }
+
+    fn is_free(&self, position: Vec2) -> bool {
+        let (center, half_size) = Self::body(position);
+        !self
+            .layout
+            .blocks
+            .iter()
+            .any(|block| !block.one_way && block.overlaps_box(center, half_size))
+    }
+
+    /// Whether moving down from `from` to `to` lands on a one-way platform.
+    fn lands_on_platform(&self, from: Vec2, to: Vec2) -> bool {
+        let (from_center, half_size) = Self::body(from);
+        let (to_center, _) = Self::body(to);
+        self.layout.blocks.iter().any(|block| {
+            block.one_way
+                && !block.overlaps_box(from_center, half_size)
+                && block.overlaps_box(to_center, half_size)
+        })
+    }
+
+    fn is_standing(&self, position: Vec2) -> bool {
+        let below = position - SWEEP_STEP * Vec2::Y;
+        !self.is_free(below) || self.lands_on_platform(position, below)
+    }
+
+    fn touches_door(&self, position: Vec2) -> bool {
+        let door_half_size = Vec2::splat(2.0);
+        let player_half_size = Vec2::new(PLAYER_HALF_WIDTH, PLAYER_TOP);
+        self.layout.doors.iter().any(|door_position| {
+            let distance = (position - *door_position).abs();
+            distance.cmplt(door_half_size + player_half_size).all()
+        })
+    }
+
+    fn enter_teleporters(&mut self, position: Vec2) {
+        for (index, (entrance, exit)) in self.layout.teleports.iter().enumerate() {
+            if position.distance(*entrance) < TELEPORTER_RADIUS + PLAYER_HALF_WIDTH
+                && self.used_teleports.insert(index)
+            {
+                self.pending_teleport_exits.push(*exit);
+            }
+        }
+    }
+
+    /// Move by `delta` until hitting an obstacle. Returns `true` if the movement was blocked.
+    fn sweep(&mut self, position: &mut Vec2, delta: Vec2) -> bool {
+        let num_steps = (delta.length() / SWEEP_STEP).ceil().max(1.0) as usize;
+        let step = delta / num_steps as f32;
+        for _ in 0..num_steps {
+            if !self.is_free(*position + step)
+                || (step.y < 0.0 && self.lands_on_platform(*position, *position + step))
+            {
+                return true;
+            }
+            *position += step;
+            if self.touches_door(*position) {
+                self.reached_door = true;
+            }
+            self.enter_teleporters(*position);
+        }
+        false
+    }
+
+    /// Simulate a flight and return where it lands, or `None` if the player falls to their death.
+    fn fly(&mut self, mut position: Vec2, flight: Flight) -> Option<Vec2> {
+        let mut velocity = Vec2::new(
+            flight.speed,
+            (2.0 * self.gravity * flight.jump_height).sqrt(),
+        );
+        let mut air_jump = flight.air_jump;
+        let mut dash = flight.dash;
+        let mut time = 0.0;
+        while time < MAX_FLIGHT_TIME && self.kill_height < position.y && !self.reached_door {
+            if velocity.y <= 0.0 {
+                if air_jump {
+                    air_jump = false;
+                    velocity.y = (2.0 * self.gravity * AIR_JUMP_HEIGHT).sqrt();
+                } else if dash {
+                    dash = false;
+                    self.sweep(
+                        &mut position,
+                        DASH_DISTANCE * flight.speed.signum() * Vec2::X,
+                    );
+                    velocity.y = 0.0;
+                }
+            }
+            velocity.y -= if 0.0 < velocity.y {
+                self.gravity
+            } else {
+                self.fall_gravity
+            } * SIMULATION_TIME_STEP;
+            if self.sweep(&mut position, velocity.x * SIMULATION_TIME_STEP * Vec2::X) {
+                velocity.x = 0.0;
+            }
+            if self.sweep(&mut position, velocity.y * SIMULATION_TIME_STEP * Vec2::Y) {
+                if velocity.y < 0.0 {
+                    return Some(position);
+                }
+                velocity.y = 0.0;
+            }
+            time += SIMULATION_TIME_STEP;
+        }
+        None
+    }
+
+    fn flights(standing: bool) -> impl Iterator<Item = Flight> {
+        let jump_height = if standing { JUMP_HEIGHT } else { 0.0 };
+        SAMPLED_SPEED_RATIOS.into_iter().flat_map(move |ratio| {
+            let speed = ratio * RUN_SPEED;
+            [(false, false), (true, false), (false, true), (true, true)]
+                .into_iter()
+                .filter(move |(air_jump, dash)| {
+                    (!*dash || speed != 0.0) && (!*air_jump || 0.0 < jump_height)
+                })
+                .map(move |(air_jump, dash)| Flight {
+                    speed,
+                    jump_height,
+                    air_jump,
+                    dash,
+                })
+        })
+    }
+
+    fn can_reach_door_from(&mut self, spawn_position: Vec2) -> bool {
+        if self.touches_door(spawn_position) {
+            return true;
+        }
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        let mut visit = |position: Vec2, queue: &mut VecDeque<Vec2>| {
+            if visited.insert((position / WALK_STEP).round().as_ivec2()) {
+                queue.push_back(position);
+            }
+        };
+        self.pending_teleport_exits.push(spawn_position);
+
+        loop {
+            // Teleporting is treated like spawning at the destination.
+            for exit in std::mem::take(&mut self.pending_teleport_exits) {
+                if let Some(landing) = self.fly(exit, Flight::FALL) {
+                    visit(landing, &mut queue);
+                }
+            }
+            if self.reached_door {
+                return true;
+            }
+            let Some(position) = queue.pop_front() else {
+                break;
+            };
+            let standing = self.is_standing(position);
+            if standing {
+                for direction in [-1.0, 1.0] {
+                    let step = direction * WALK_STEP * Vec2::X;
+                    let mut walked_to = position;
+                    if self.sweep(&mut walked_to, step) {
+                        walked_to = position;
+                        if self.sweep(&mut walked_to, MAX_STEP_UP * Vec2::Y)
+                            || self.sweep(&mut walked_to, step)
+                        {
+                            continue;
+                        }
+                    }
+                    let mut settled = walked_to;
+                    if self.sweep(&mut settled, -(MAX_STEP_UP + SWEEP_STEP) * Vec2::Y) {
+                        visit(settled, &mut queue);
+                    } else {
+                        // Walked off a ledge. Coyote time still allows jumping from there.
+                        visit(walked_to, &mut queue);
+                    }
+                }
+            }
+            for flight in Self::flights(true).chain(Self::flights(false)) {
+                if let Some(landing) = self.fly(position, flight) {
+                    visit(landing, &mut queue);
+                }
+            }
+        }
+        self.reached_door
+    }
+}
+
+#[derive(Resource, Default)]
+struct LevelValidationReport(Option<Vec<LevelProblem>>);
+
+fn level_validation_section(world: &mut World) -> impl FnMut(&mut World, &mut egui::Ui) {
+    let mut system_state = SystemState::<(
+        Res<State<YoleckEditorState>>,
+        ResMut<LevelValidationReport>,
+        Query<(&Vpeol3dPosition, &PlayerSlot), With<IsPlayer>>,
+        Query<(&Vpeol3dPosition, &DoorLock), With<IsDoor>>,
+        Query<&KeyColor, With<IsKey>>,
+        Query<&Vpeol3dPosition, With<IsCannon>>,
+        Query<(&Vpeol3dPosition, &Teleporter, &YoleckEntityUuid)>,
+        Query<
+            (
+                &Vpeol3dPosition,
+                Option<&Vpeol3dScale>,
+                Option<&Vpeol3dRotatation>,
+                Has<OneWayPlatform>,
+            ),
+            (With<IsBlock>, Without<IsGate>),
+        >,
+        Query<&Vpeol3dPosition, With<IsKillPlane>>,
+    )>::new(world);
+
+    move |world, ui| {
+        let (
+            editor_state,
+            mut report,
+            players_query,
+            doors_query,
+            keys_query,
+            cannons_query,
+            teleporters_query,
+            blocks_query,
+            kill_planes_query,
+        ) = system_state.get_mut(world);
+        if !matches!(editor_state.get(), YoleckEditorState::EditorActive) {
+            return;
+        }
+        if ui.button("Validate Level").clicked() {
+            let layout = LevelLayout {
+                players: players_query
+                    .iter()
+                    .map(|(position, slot)| (slot.index, position.0.truncate()))
+                    .collect(),
+                doors: doors_query
+                    .iter()
+                    .map(|(position, _)| position.0.truncate())
+                    .collect(),
+                locked_doors: doors_query
+                    .iter()
+                    .filter_map(|(position, door_lock)| {
+                        Some((position.0.truncate(), door_lock.key?))
+                    })
+                    .collect(),
+                keys: keys_query.iter().copied().collect(),
+                teleports: resolve_teleports(
+                    &teleporters_query
+                        .iter()
+                        .map(|(position, teleporter, uuid)| {
+                            (
+                                Some(uuid.get()),
+                                position.0.truncate(),
+                                teleporter.destination,
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                cannons: cannons_query
+                    .iter()
+                    .map(|position| position.0.truncate())
+                    .collect(),
+                blocks: blocks_query
+                    .iter()
+                    .map(|(position, scale, rotation, one_way)| {
+                        LayoutBlock::new(
+                            position.0,
+                            scale.map_or(Vec3::ONE, |scale| scale.0),
+                            rotation.map_or(Quat::IDENTITY, |rotation| rotation.0),
+                            one_way,
+                        )
+                    })
+                    .collect(),
+                kill_planes: kill_planes_query
+                    .iter()
+                    .map(|position| position.0.y)
+                    .collect(),
+            };
+            report.0 = Some(layout.validate());
+        }
+        match &report.0 {
+            None => {}
+            Some(problems) if problems.is_empty() => {
+                ui.label("No problems found");
+            }
+            Some(problems) => {
+                for problem in problems.iter() {
+                    ui.colored_label(egui::Color32::RED, problem.to_string());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(center: Vec2, size: Vec2, angle: f32) -> LayoutBlock {
+        LayoutBlock::new(
+            center.extend(0.0),
+            size.extend(1.0),
+            Quat::from_rotation_z(angle),
+            false,
+        )
+    }
+
+    /// A floor whose top is at `y = 0`, with a player standing on it and a door at `door`.
+    fn layout_with_door(door: Vec2) -> LevelLayout {
+        LevelLayout {
+            players: vec![(0, Vec2::new(-5.0, FLOAT_HEIGHT + 0.05))],
+            doors: vec![door],
+            blocks: vec![block(Vec2::new(0.0, -0.5), Vec2::new(40.0, 1.0), 0.0)],
+            kill_planes: vec![-20.0],
+            ..Default::default()
+        }
+    }
+
+    fn descriptions(layout: &LevelLayout) -> Vec<String> {
+        layout
+            .validate()
+            .into_iter()
+            .map(|problem| problem.description)
+            .collect()
+    }
+
+    #[test]
+    fn rotated_block_overlap() {
+        let diagonal = block(Vec2::ZERO, Vec2::new(4.0, 1.0), std::f32::consts::FRAC_PI_4);
+        assert!(diagonal.overlaps_box(Vec2::new(1.0, 1.0), Vec2::splat(0.1)));
+        // Inside the block's axis-aligned bounding box, but off to the side of the block itself.
+        assert!(!diagonal.overlaps_box(Vec2::new(1.0, -1.0), Vec2::splat(0.1)));
+    }
+
+    #[test]
+    fn teleporter_loop_resolves_both_ways() {
+        let a = Uuid::from_u128(1);
+        let b = Uuid::from_u128(2);
+        let teleports = resolve_teleports(&[
+            (Some(a), Vec2::new(0.0, 0.0), Some(b)),
+            (Some(b), Vec2::new(10.0, 0.0), Some(a)),
+        ]);
+        assert_eq!(
+            teleports,
+            vec![
+                (Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0)),
+                (Vec2::new(10.0, 0.0), Vec2::new(0.0, 0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn teleporter_without_destination_is_skipped() {
+        let teleports = resolve_teleports(&[
+            (
+                Some(Uuid::from_u128(1)),
+                Vec2::ZERO,
+                Some(Uuid::from_u128(3)),
+            ),
+            (Some(Uuid::from_u128(2)), Vec2::X, None),
+        ]);
+        assert!(teleports.is_empty());
+    }
+
+    #[test]
+    fn teleporter_loop_does_not_stop_reachability_check() {
+        let mut layout = layout_with_door(Vec2::new(10.0, 40.0));
+        layout.teleports = vec![
+            (Vec2::new(-2.0, 1.0), Vec2::new(2.0, 1.0)),
+            (Vec2::new(2.0, 1.0), Vec2::new(-2.0, 1.0)),
+        ];
+        assert_eq!(
+            descriptions(&layout),
+            ["No Door is reachable from the Player in slot 1"]
+        );
+    }
+
+    #[test]
+    fn door_that_requires_a_missing_key() {
+        let mut layout = layout_with_door(Vec2::new(5.0, 2.0));
+        layout.locked_doors = vec![(Vec2::new(5.0, 2.0), KeyColor::Blue)];
+        assert_eq!(
+            descriptions(&layout),
+            ["Door requires a Blue Key, but there is none"]
+        );
+    }
+
+    #[test]
+    fn door_that_requires_a_key_in_the_level() {
+        let mut layout = layout_with_door(Vec2::new(5.0, 2.0));
+        layout.locked_doors = vec![(Vec2::new(5.0, 2.0), KeyColor::Blue)];
+        layout.keys.insert(KeyColor::Blue);
+        assert!(descriptions(&layout).is_empty());
+    }
+}
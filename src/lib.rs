@@ -2,14 +2,26 @@ mod animating;
 mod arena;
 mod arrow;
 mod camera;
+mod camera_zone;
 mod cannon;
+mod collectible;
 mod door;
 mod explosion;
+mod force_zone;
+mod hazard;
 mod level_handling;
+mod level_validation;
+mod logic;
 mod menu;
 mod missile;
+mod missile_indicators;
+mod moving_block;
 mod player;
 mod player_controls;
+mod playtest;
+mod snapping;
+mod solver_groups;
+mod teleporter;
 mod utils;
 
 use bevy::prelude::*;
@@ -20,18 +32,33 @@ use self::animating::AnimatingPlugin;
 use self::arena::ArenaPlugin;
 use self::arrow::ArrowPlugin;
 use self::camera::MazeOfManyMissilesCameraPlugin;
+use self::camera_zone::CameraZonePlugin;
 use self::cannon::CannonPlugin;
+use self::collectible::CollectiblePlugin;
 use self::door::DoorPlugin;
-use self::explosion::ExplosionPlugin;
+use self::explosion::{ExplosionPlugin, ShowExplosionFields};
+use self::force_zone::ForceZonePlugin;
+use self::hazard::HazardPlugin;
 use self::level_handling::{LevelHandlingPlugin, LevelProgress};
+use self::level_validation::LevelValidationPlugin;
+use self::logic::LogicPlugin;
 use self::menu::MenuPlugin;
 use self::missile::MissilePlugin;
-use self::player::PlayerPlugin;
+use self::missile_indicators::MissileIndicatorsPlugin;
+use self::moving_block::MovingBlockPlugin;
+use self::player::{NumberOfPlayers, PlayerPlugin, MAX_PLAYERS};
 use self::player_controls::PlayerControlsPlugin;
+use self::playtest::PlaytestFromHerePlugin;
+use self::snapping::SnappingPlugin;
+use self::teleporter::TeleporterPlugin;
+
+pub use self::level_validation::validate_level_files;
 
 pub struct MazeOfManyMissilesPlugin {
     pub is_editor: bool,
     pub start_at_level: Option<String>,
+    pub num_players: usize,
+    pub debug_explosions: bool,
 }
 
 impl Plugin for MazeOfManyMissilesPlugin {
@@ -44,12 +71,17 @@ impl Plugin for MazeOfManyMissilesPlugin {
             ),
         );
         app.add_state::<AppState>();
+        app.insert_resource(NumberOfPlayers(self.num_players.clamp(1, MAX_PLAYERS)));
+        app.insert_resource(ShowExplosionFields(self.debug_explosions));
         app.add_plugins(MazeOfManyMissilesCameraPlugin);
         if self.is_editor {
             app.add_plugins(YoleckSyncWithEditorState {
                 when_editor: AppState::Editor,
                 when_game: AppState::Game,
             });
+            app.add_plugins(PlaytestFromHerePlugin);
+            app.add_plugins(LevelValidationPlugin);
+            app.add_plugins(SnappingPlugin);
         } else {
             app.add_plugins(MenuPlugin);
             app.add_plugins(LevelHandlingPlugin);
@@ -72,12 +104,20 @@ impl Plugin for MazeOfManyMissilesPlugin {
         app.add_plugins(AnimatingPlugin);
         app.add_plugins(PlayerPlugin);
         app.add_plugins(ArenaPlugin);
+        app.add_plugins(MovingBlockPlugin);
         app.add_plugins(PlayerControlsPlugin);
         app.add_plugins(CannonPlugin);
         app.add_plugins(MissilePlugin);
+        app.add_plugins(MissileIndicatorsPlugin);
         app.add_plugins(ExplosionPlugin);
         app.add_plugins(DoorPlugin);
+        app.add_plugins(LogicPlugin);
+        app.add_plugins(CollectiblePlugin);
+        app.add_plugins(TeleporterPlugin);
+        app.add_plugins(ForceZonePlugin);
+        app.add_plugins(HazardPlugin);
         app.add_plugins(ArrowPlugin);
+        app.add_plugins(CameraZonePlugin);
         //app.add_plugins(FloatingTextPlugin);
 
         app.add_systems(Update, enable_disable_physics);
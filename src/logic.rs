@@ -0,0 +1,430 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, Uuid};
+use bevy_rapier2d::prelude::*;
+use bevy_yoleck::exclusive_systems::{YoleckExclusiveSystemDirective, YoleckExclusiveSystemsQueue};
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol::vpeol_read_click_on_entity;
+use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dRotatation, Vpeol3dScale};
+use bevy_yoleck::{yoleck_exclusive_system_cancellable, yoleck_map_entity_to_uuid, YoleckManaged};
+use serde::{Deserialize, Serialize};
+
+use crate::arena::{populate_block, BlockSurface, IsBlock};
+use crate::missile::ExplodesMissileOnImpact;
+use crate::player::IsPlayer;
+use crate::player_controls::FLOAT_HEIGHT;
+use crate::utils::{
+    collision_started_events_both_ways, keep_zone_behind_geometry, resize_with_corner_knobs,
+    CachedPbrMaker,
+};
+use crate::{AppState, During};
+
+/// Switches, pressure plates and trigger zones, and the entities that can be wired to them.
+pub struct LogicPlugin;
+
+impl Plugin for LogicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LogicMaterials>();
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("Switch")
+                .with_uuid()
+                .with::<Vpeol3dPosition>()
+                .insert_on_init(|| (IsSwitch, LogicSignal(false)))
+        });
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("PressurePlate")
+                .with_uuid()
+                .with::<Vpeol3dPosition>()
+                .insert_on_init(|| (IsPressurePlate, LogicSignal(false)))
+        });
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("TriggerZone")
+                .with_uuid()
+                .with::<Vpeol3dPosition>()
+                .with::<Vpeol3dScale>()
+                .insert_on_init(|| (IsTriggerZone, LogicSignal(false)))
+        });
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("Gate")
+                .with::<Vpeol3dPosition>()
+                .with::<Vpeol3dScale>()
+                .with::<Vpeol3dRotatation>()
+                .with::<BlockSurface>()
+                .with::<LogicInputs>()
+                .insert_on_init(|| (IsBlock, IsGate, ExplodesMissileOnImpact))
+        });
+
+        app.add_yoleck_edit_system(edit_logic_inputs);
+        app.add_yoleck_edit_system(resize_with_corner_knobs::<With<IsTriggerZone>>);
+        app.add_yoleck_edit_system(keep_zone_behind_geometry::<With<IsTriggerZone>>);
+
+        app.add_systems(
+            YoleckSchedule::Populate,
+            (
+                populate_switch,
+                populate_pressure_plate,
+                populate_trigger_zone,
+                populate_gate.after(populate_block),
+                populate_logic_receiver,
+            ),
+        );
+        app.add_systems(Update, draw_wiring.run_if(in_state(AppState::Editor)));
+        app.add_systems(
+            Update,
+            (
+                (toggle_switches, press_pressure_plates, enter_trigger_zones),
+                update_logic_receivers,
+                open_and_close_gates,
+            )
+                .chain()
+                .in_set(During::Gameplay),
+        );
+        app.add_systems(Update, update_signal_materials);
+    }
+}
+
+#[derive(Component)]
+pub struct IsSwitch;
+
+#[derive(Component)]
+pub struct IsPressurePlate;
+
+/// A rectangle that turns on for good once a player enters it.
+#[derive(Component)]
+pub struct IsTriggerZone;
+
+/// A block that disappears while it is powered.
+#[derive(Component)]
+pub struct IsGate;
+
+/// The output of a switch, a pressure plate or a trigger zone.
+#[derive(Component)]
+pub struct LogicSignal(pub bool);
+
+/// Wiring from switches and pressure plates to the entity that reacts to them.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct LogicInputs {
+    pub sources: Vec<Uuid>,
+    /// Only power the entity when all its sources are on, instead of when any of them is.
+    pub require_all: bool,
+    pub inverted: bool,
+}
+
+impl LogicInputs {
+    /// `None` if nothing is wired to the entity.
+    fn evaluate(&self, is_signal_on: impl Fn(Uuid) -> bool) -> Option<bool> {
+        if self.sources.is_empty() {
+            return None;
+        }
+        let mut sources = self.sources.iter().map(|source| is_signal_on(*source));
+        let powered = if self.require_all {
+            sources.all(|on| on)
+        } else {
+            sources.any(|on| on)
+        };
+        Some(powered != self.inverted)
+    }
+}
+
+/// Whether an entity with [`LogicInputs`] is currently powered. Entities that are not wired to
+/// anything keep the value they were populated with.
+#[derive(Component)]
+pub struct LogicPowered(pub bool);
+
+#[derive(Resource)]
+struct LogicMaterials {
+    switch_mesh: Handle<Mesh>,
+    pressure_plate_mesh: Handle<Mesh>,
+    off: Handle<StandardMaterial>,
+    on: Handle<StandardMaterial>,
+    gate: Handle<StandardMaterial>,
+}
+
+impl FromWorld for LogicMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let switch_mesh = meshes.add(Mesh::from(shape::Box::new(0.6, 1.0, 0.6)));
+        let pressure_plate_mesh = meshes.add(Mesh::from(shape::Box::new(
+            2.0 * PRESSURE_PLATE_HALF_WIDTH,
+            0.3,
+            1.0,
+        )));
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        Self {
+            switch_mesh,
+            pressure_plate_mesh,
+            off: materials.add(Color::MAROON.into()),
+            on: materials.add(Color::LIME_GREEN.into()),
+            gate: materials.add(Color::MIDNIGHT_BLUE.into()),
+        }
+    }
+}
+
+const PRESSURE_PLATE_HALF_WIDTH: f32 = 1.0;
+
+/// How far above a pressure plate a player can be and still press it. Tnua keeps the players
+/// floating above the ground, so they never actually touch the plate.
+const PRESSURE_PLATE_REACH: f32 = FLOAT_HEIGHT + 1.5;
+
+const WIRE_COLOR: Color = Color::GOLD;
+
+fn populate_switch(
+    mut populate: YoleckPopulate<(), With<IsSwitch>>,
+    materials: Res<LogicMaterials>,
+) {
+    populate.populate(|ctx, mut cmd, ()| {
+        if ctx.is_first_time() {
+            cmd.insert(PbrBundle {
+                mesh: materials.switch_mesh.clone(),
+                material: materials.off.clone(),
+                ..Default::default()
+            });
+        }
+        cmd.insert(Collider::cuboid(0.5, 0.75));
+        cmd.insert(Sensor);
+    });
+}
+
+fn populate_pressure_plate(
+    mut populate: YoleckPopulate<(), With<IsPressurePlate>>,
+    materials: Res<LogicMaterials>,
+) {
+    populate.populate(|ctx, mut cmd, ()| {
+        if ctx.is_first_time() {
+            cmd.insert(PbrBundle {
+                mesh: materials.pressure_plate_mesh.clone(),
+                material: materials.off.clone(),
+                ..Default::default()
+            });
+        }
+    });
+}
+
+fn populate_trigger_zone(
+    mut populate: YoleckPopulate<(), With<IsTriggerZone>>,
+    mut pbr: CachedPbrMaker,
+) {
+    populate.populate(|ctx, mut cmd, ()| {
+        if ctx.is_in_editor() && ctx.is_first_time() {
+            cmd.insert(pbr.make_pbr_with(
+                || Mesh::from(shape::Box::new(1.0, 1.0, 0.01)),
+                || StandardMaterial {
+                    base_color: Color::rgba(1.0, 0.8, 0.0, 0.1),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..Default::default()
+                },
+            ));
+        }
+    });
+}
+
+fn populate_gate(mut populate: YoleckPopulate<(), With<IsGate>>, materials: Res<LogicMaterials>) {
+    populate.populate(|_ctx, mut cmd, ()| {
+        cmd.insert(materials.gate.clone());
+    });
+}
+
+fn populate_logic_receiver(mut populate: YoleckPopulate<Has<IsGate>, With<LogicInputs>>) {
+    populate.populate(|ctx, mut cmd, is_gate| {
+        if ctx.is_in_editor() || !ctx.is_first_time() {
+            return;
+        }
+        // Gates stay closed unless something opens them, while cannons and doors are active
+        // unless something turns them off.
+        cmd.insert(LogicPowered(!is_gate));
+    });
+}
+
+fn edit_logic_inputs(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut LogicInputs>,
+    sources_query: Query<(&YoleckEntityUuid, &YoleckManaged), With<LogicSignal>>,
+    mut exclusive_queue: ResMut<YoleckExclusiveSystemsQueue>,
+) {
+    let Ok(mut inputs) = edit.get_single_mut() else {
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        ui.label("Inputs:");
+        ui.checkbox(&mut inputs.require_all, "Require All");
+        ui.checkbox(&mut inputs.inverted, "Inverted");
+    });
+    let mut to_remove = None;
+    for (i, source) in inputs.sources.iter().enumerate() {
+        let name = sources_query
+            .iter()
+            .find(|(uuid, _)| uuid.get() == *source)
+            .map_or("<missing>", |(_, managed)| managed.type_name.as_str());
+        ui.horizontal(|ui| {
+            ui.label(format!("{} {}", name, source.simple()));
+            if ui.button("Remove").clicked() {
+                to_remove = Some(i);
+            }
+        });
+    }
+    if let Some(to_remove) = to_remove {
+        inputs.sources.remove(to_remove);
+    }
+    if ui.button("Add Input").clicked() {
+        exclusive_queue.push_back(
+            vpeol_read_click_on_entity::<With<LogicSignal>>
+                .pipe(yoleck_map_entity_to_uuid)
+                .pipe(
+                    |In(source): In<Option<Uuid>>, mut edit: YoleckEdit<&mut LogicInputs>| {
+                        let Ok(mut inputs) = edit.get_single_mut() else {
+                            return YoleckExclusiveSystemDirective::Finished;
+                        };
+                        let Some(source) = source else {
+                            return YoleckExclusiveSystemDirective::Listening;
+                        };
+                        if !inputs.sources.contains(&source) {
+                            inputs.sources.push(source);
+                        }
+                        YoleckExclusiveSystemDirective::Finished
+                    },
+                )
+                .pipe(yoleck_exclusive_system_cancellable),
+        );
+    }
+}
+
+/// The [`YoleckUuidRegistry`] only remembers the last entity populated with each UUID, which is
+/// not good enough in the editor (where playtests load a second copy of the level), so sources are
+/// looked up by their level as well.
+fn signal_sources_by_level<'a>(
+    sources: impl Iterator<Item = (&'a YoleckEntityUuid, &'a YoleckBelongsToLevel, bool)>,
+) -> HashMap<(Entity, Uuid), bool> {
+    sources
+        .map(|(uuid, belongs_to_level, on)| ((belongs_to_level.level, uuid.get()), on))
+        .collect()
+}
+
+fn draw_wiring(
+    receivers_query: Query<(&LogicInputs, &GlobalTransform, &YoleckBelongsToLevel)>,
+    sources_query: Query<(&YoleckEntityUuid, &GlobalTransform, &YoleckBelongsToLevel)>,
+    mut gizmos: Gizmos,
+) {
+    let source_positions = sources_query
+        .iter()
+        .map(|(uuid, transform, belongs_to_level)| {
+            (
+                (belongs_to_level.level, uuid.get()),
+                transform.translation(),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    for (inputs, transform, belongs_to_level) in receivers_query.iter() {
+        for source in inputs.sources.iter() {
+            if let Some(source_position) = source_positions.get(&(belongs_to_level.level, *source))
+            {
+                gizmos.line(*source_position, transform.translation(), WIRE_COLOR);
+            }
+        }
+    }
+}
+
+fn toggle_switches(
+    mut reader: EventReader<CollisionEvent>,
+    players_query: Query<(), With<IsPlayer>>,
+    mut switches_query: Query<&mut LogicSignal, With<IsSwitch>>,
+) {
+    for (e1, e2) in collision_started_events_both_ways(&mut reader) {
+        if !players_query.contains(e1) {
+            continue;
+        }
+        if let Ok(mut signal) = switches_query.get_mut(e2) {
+            signal.0 = !signal.0;
+        }
+    }
+}
+
+fn press_pressure_plates(
+    players_query: Query<&GlobalTransform, With<IsPlayer>>,
+    mut plates_query: Query<(&GlobalTransform, &mut LogicSignal), With<IsPressurePlate>>,
+) {
+    for (plate_transform, mut signal) in plates_query.iter_mut() {
+        let plate_position = plate_transform.translation().truncate();
+        let pressed = players_query.iter().any(|player_transform| {
+            let offset = player_transform.translation().truncate() - plate_position;
+            offset.x.abs() < PRESSURE_PLATE_HALF_WIDTH
+                && 0.0 < offset.y
+                && offset.y < PRESSURE_PLATE_REACH
+        });
+        if signal.0 != pressed {
+            signal.0 = pressed;
+        }
+    }
+}
+
+fn enter_trigger_zones(
+    players_query: Query<&GlobalTransform, With<IsPlayer>>,
+    mut zones_query: Query<(&GlobalTransform, &mut LogicSignal), With<IsTriggerZone>>,
+) {
+    for (zone_transform, mut signal) in zones_query.iter_mut() {
+        if signal.0 {
+            continue;
+        }
+        let (scale, _, translation) = zone_transform.to_scale_rotation_translation();
+        let rect = Rect::from_center_size(translation.truncate(), scale.truncate());
+        if players_query
+            .iter()
+            .any(|player_transform| rect.contains(player_transform.translation().truncate()))
+        {
+            signal.0 = true;
+        }
+    }
+}
+
+fn update_logic_receivers(
+    sources_query: Query<(&YoleckEntityUuid, &YoleckBelongsToLevel, &LogicSignal)>,
+    mut receivers_query: Query<(&LogicInputs, &YoleckBelongsToLevel, &mut LogicPowered)>,
+) {
+    let signals = signal_sources_by_level(
+        sources_query
+            .iter()
+            .map(|(uuid, belongs_to_level, signal)| (uuid, belongs_to_level, signal.0)),
+    );
+    for (inputs, belongs_to_level, mut powered) in receivers_query.iter_mut() {
+        let Some(new_powered) = inputs.evaluate(|source| {
+            signals
+                .get(&(belongs_to_level.level, source))
+                .copied()
+                .unwrap_or(false)
+        }) else {
+            continue;
+        };
+        if powered.0 != new_powered {
+            powered.0 = new_powered;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn open_and_close_gates(
+    query: Query<(Entity, &LogicPowered), (With<IsGate>, Changed<LogicPowered>)>,
+    mut commands: Commands,
+) {
+    for (entity, powered) in query.iter() {
+        let mut cmd = commands.entity(entity);
+        if powered.0 {
+            cmd.insert((ColliderDisabled, Visibility::Hidden));
+        } else {
+            cmd.remove::<ColliderDisabled>();
+            cmd.insert(Visibility::Inherited);
+        }
+    }
+}
+
+fn update_signal_materials(
+    mut query: Query<(&LogicSignal, &mut Handle<StandardMaterial>), Changed<LogicSignal>>,
+    materials: Res<LogicMaterials>,
+) {
+    for (signal, mut material) in query.iter_mut() {
+        *material = if signal.0 {
+            materials.on.clone()
+        } else {
+            materials.off.clone()
+        };
+    }
+}
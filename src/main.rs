@@ -4,6 +4,8 @@
 // Feel free to delete this line.
 #![allow(clippy::too_many_arguments, clippy::type_complexity)]
 
+use std::path::Path;
+
 use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
@@ -16,7 +18,7 @@ use bevy_turborand::prelude::RngPlugin;
 use bevy_yoleck::vpeol_3d::{Vpeol3dPluginForEditor, Vpeol3dPluginForGame};
 use bevy_yoleck::{YoleckPluginForEditor, YoleckPluginForGame};
 use clap::Parser;
-use maze_of_many_missiles::{ActionForKbgp, MazeOfManyMissilesPlugin};
+use maze_of_many_missiles::{validate_level_files, ActionForKbgp, MazeOfManyMissilesPlugin};
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -24,11 +26,28 @@ struct Args {
     editor: bool,
     #[clap(long)]
     level: Option<String>,
+    #[clap(long, default_value_t = 1)]
+    players: usize,
+    #[clap(long)]
+    validate_levels: bool,
+    #[clap(long)]
+    debug_explosions: bool,
 }
 
 fn main() {
     let args = Args::parse();
 
+    if args.validate_levels {
+        let mut found_problems = false;
+        for (level, problems) in validate_level_files(Path::new("assets/levels")) {
+            for problem in problems {
+                println!("{}: {}", level, problem);
+                found_problems = true;
+            }
+        }
+        std::process::exit(if found_problems { 1 } else { 0 });
+    }
+
     let mut app = App::new();
 
     // Without this, WASM builds on Itch.io will try to access the non-existing .meta files and
@@ -97,6 +116,8 @@ fn main() {
     app.add_plugins(MazeOfManyMissilesPlugin {
         is_editor: args.editor,
         start_at_level: args.level,
+        num_players: args.players,
+        debug_explosions: args.debug_explosions,
     });
 
     app.run();
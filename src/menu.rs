@@ -1,9 +1,13 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use bevy_egui_kbgp::prelude::*;
+use bevy_pkv::PkvStore;
 use bevy_yoleck::prelude::*;
 
+use crate::camera::SplitScreen;
+use crate::collectible::GemsRecord;
 use crate::level_handling::LevelProgress;
+use crate::player::{NumberOfPlayers, MAX_PLAYERS};
 use crate::{ActionForKbgp, AppState, During};
 
 #[derive()]
@@ -141,7 +145,12 @@ fn format_level_name(filename: &str) -> String {
         .replace('_', " ")
 }
 
-fn main_menu(mut frame_ui: ResMut<FrameUi>, mut next_state: ResMut<NextState<AppState>>) {
+fn main_menu(
+    mut frame_ui: ResMut<FrameUi>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut number_of_players: ResMut<NumberOfPlayers>,
+    mut split_screen: ResMut<SplitScreen>,
+) {
     let Some(ui) = frame_ui.0.as_mut() else {
         return;
     };
@@ -156,6 +165,25 @@ fn main_menu(mut frame_ui: ResMut<FrameUi>, mut next_state: ResMut<NextState<App
         ui.kbgp_clear_input();
         ui.kbgp_set_focus_label(FocusLabel::NextLevel);
     }
+    if ui
+        .button(format!("Players: {}", number_of_players.0))
+        .kbgp_navigation()
+        .clicked()
+    {
+        number_of_players.0 = number_of_players.0 % MAX_PLAYERS + 1;
+    }
+    if 1 < number_of_players.0
+        && ui
+            .button(if split_screen.enabled {
+                "Split Screen: On"
+            } else {
+                "Split Screen: Off"
+            })
+            .kbgp_navigation()
+            .clicked()
+    {
+        split_screen.enabled = !split_screen.enabled;
+    }
 }
 
 fn pause_menu(mut frame_ui: ResMut<FrameUi>, mut next_state: ResMut<NextState<AppState>>) {
@@ -235,6 +263,7 @@ fn level_select_menu(
     mut next_state: ResMut<NextState<AppState>>,
     level_index_assets: Res<Assets<YoleckLevelIndex>>,
     mut level_progress: ResMut<LevelProgress>,
+    pkv: Res<PkvStore>,
 ) {
     let Some(ui) = frame_ui.0.as_mut() else {
         return;
@@ -306,6 +335,20 @@ fn level_select_menu(
                     },
                 );
             }
+            if let Some(gems_record) = GemsRecord::load(&pkv, &level.filename) {
+                button_text.append(
+                    &format!("{}/{} gems", gems_record.collected, gems_record.total),
+                    4.0,
+                    egui::TextFormat {
+                        font_id: egui::FontId {
+                            size: 24.0,
+                            family: egui::FontFamily::Proportional,
+                        },
+                        color: egui::Color32::LIGHT_BLUE,
+                        ..Default::default()
+                    },
+                );
+            }
             let mut response = ui.add(egui::Button::new(button_text)).kbgp_navigation();
             if index + 1 == level_progress.num_levels_available {
                 response = response.kbgp_focus_label(FocusLabel::NextLevel);
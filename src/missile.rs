@@ -1,12 +1,15 @@
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
-use bevy_yoleck::YoleckBelongsToLevel;
+use bevy_yoleck::prelude::*;
 use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
 
-use crate::explosion::{PushableByExplosion, StartExplosion};
+use crate::cannon::CannonHitPoints;
+use crate::explosion::{ExplosionConfig, ExplosionFalloff, PushableByExplosion, StartExplosion};
+use crate::force_zone::ZoneEffects;
 use crate::player::IsPlayer;
-use crate::utils::collision_started_events_both_ways;
-use crate::During;
+use crate::player_controls::{DeflectState, DEFLECT_RADIUS};
+use crate::{solver_groups, During};
 
 pub struct MissilePlugin;
 
@@ -14,7 +17,19 @@ impl Plugin for MissilePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<LaunchMissile>();
         app.add_systems(Update, launch_missiles);
-        app.add_systems(Update, control_missiles.in_set(During::Gameplay));
+        app.add_systems(
+            Update,
+            (
+                deflect_missiles,
+                control_missiles,
+                split_cluster_missiles,
+                light_proximity_fuses,
+                burn_fuses,
+            )
+                .chain()
+                .in_set(During::Gameplay),
+        );
+        app.add_systems(Update, flash_burning_fuses.in_set(During::Gameplay));
         app.add_systems(Update, explode_missiles_on_impact);
     }
 }
@@ -23,20 +38,173 @@ impl Plugin for MissilePlugin {
 pub struct ExplodesMissileOnImpact;
 
 #[derive(Component)]
-struct MissileConfig {
+pub struct MissileConfig {
     speed: f32,
     acceleration: f32,
     angular_speed: f32,
     angular_acceleration: f32,
 }
 
+impl Default for MissileConfig {
+    fn default() -> Self {
+        Self {
+            speed: 30.0,
+            acceleration: 400.0,
+            angular_speed: 20.0,
+            angular_acceleration: 400.0,
+        }
+    }
+}
+
+/// The kind of missiles a cannon fires.
+#[derive(
+    Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize, Component, YoleckComponent,
+)]
+pub enum MissileKind {
+    #[default]
+    Regular,
+    /// Splits into several small missiles after `split_after` seconds, or once it gets within
+    /// `split_distance` of a player.
+    Cluster {
+        split_after: f32,
+        split_distance: f32,
+        fragments: usize,
+    },
+    /// A slower, smaller missile - what cluster missiles split into.
+    Small,
+}
+
+impl MissileKind {
+    pub const ALL: [MissileKind; 3] = [
+        MissileKind::Regular,
+        MissileKind::Cluster {
+            split_after: 1.0,
+            split_distance: 8.0,
+            fragments: 3,
+        },
+        MissileKind::Small,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MissileKind::Regular => "Regular",
+            MissileKind::Cluster { .. } => "Cluster",
+            MissileKind::Small => "Small",
+        }
+    }
+
+    pub fn config(&self) -> MissileConfig {
+        match self {
+            MissileKind::Regular | MissileKind::Cluster { .. } => MissileConfig::default(),
+            MissileKind::Small => MissileConfig {
+                speed: 20.0,
+                acceleration: 300.0,
+                angular_speed: 15.0,
+                angular_acceleration: 300.0,
+            },
+        }
+    }
+
+    pub fn explosion(&self) -> ExplosionConfig {
+        match self {
+            MissileKind::Regular => ExplosionConfig::default(),
+            MissileKind::Cluster { .. } => ExplosionConfig {
+                radius: 7.0,
+                duration: 0.35,
+                force: 1200.0,
+                falloff: ExplosionFalloff::Inverse,
+            },
+            MissileKind::Small => ExplosionConfig {
+                radius: 3.5,
+                duration: 0.2,
+                force: 1500.0,
+                falloff: ExplosionFalloff::InverseSquare,
+            },
+        }
+    }
+
+    fn scale(&self) -> f32 {
+        match self {
+            MissileKind::Regular | MissileKind::Cluster { .. } => 1.0,
+            MissileKind::Small => 0.5,
+        }
+    }
+}
+
+/// Makes missiles detonate near the players, instead of only on impact.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct MissileFuse {
+    /// The fuse is lit once the missile gets this close to a player. If `None`, the missile only
+    /// explodes on impact.
+    pub proximity: Option<f32>,
+    /// How long, in seconds, the missile flashes between lighting the fuse and detonating.
+    pub delay: f32,
+}
+
+impl Default for MissileFuse {
+    fn default() -> Self {
+        Self {
+            proximity: None,
+            delay: 0.5,
+        }
+    }
+}
+
+/// A lit fuse. The missile detonates when the timer finishes.
+#[derive(Component)]
+struct BurningFuse(Timer);
+
+/// How many times per second a missile with a lit fuse flashes.
+const FUSE_FLASH_FREQUENCY: f32 = 8.0;
+
+/// When a cluster missile splits, and into how many small missiles.
+#[derive(Component)]
+struct ClusterSplit {
+    timer: Timer,
+    split_distance: f32,
+    fragments: usize,
+}
+
+/// The angle between the first and the last small missiles a cluster missile splits into.
+const CLUSTER_SPREAD: f32 = std::f32::consts::FRAC_PI_2;
+
+/// What a missile is homing in on.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub enum MissileTarget {
+    #[default]
+    None,
+    /// The closest player. Picked anew every frame, and only kept so that the missile knows who it
+    /// was chasing when that player teleports.
+    Player(Entity),
+    /// Where a player was last seen - e.g. the teleporter they went through. Once the missile gets
+    /// there it picks a new target.
+    LastSeenAt(Vec2),
+}
+
+/// Marks a missile that a player deflected. It goes after the cannons and the other missiles instead
+/// of the players.
+#[derive(Component)]
+pub struct Deflected;
+
+/// How close a missile needs to get to [`MissileTarget::LastSeenAt`] before it picks a new target.
+const REACQUIRE_TARGET_DISTANCE: f32 = 2.0;
+
 #[derive(Event, Debug)]
 pub struct LaunchMissile {
     pub level: Entity,
     pub position: Vec2,
     pub direction: Vec2,
+    pub kind: MissileKind,
+    pub fuse: MissileFuse,
+    /// The entity that fired the missile, which the missile won't explode on until it gets clear
+    /// of it.
+    pub launcher: Option<Entity>,
 }
 
+/// The entity a missile was fired from, while the missile is still inside it.
+#[derive(Component)]
+struct LeavingLauncher(Entity);
+
 fn launch_missiles(
     mut reader: EventReader<LaunchMissile>,
     mut commands: Commands,
@@ -48,96 +216,410 @@ fn launch_missiles(
             transform: Transform {
                 translation: event.position.extend(0.0),
                 rotation: Quat::from_rotation_arc_2d(Vec2::X, event.direction),
-                scale: Vec3::ONE,
+                scale: Vec3::splat(event.kind.scale()),
             },
             ..Default::default()
         });
         cmd.insert(YoleckBelongsToLevel { level: event.level });
 
-        cmd.insert(MissileConfig {
-            speed: 30.0,
-            acceleration: 400.0,
-            angular_speed: 20.0,
-            angular_acceleration: 400.0,
-        });
+        let missile_config = event.kind.config();
+        let initial_velocity = Velocity::linear(event.direction * missile_config.speed);
+        cmd.insert(missile_config);
+        cmd.insert(MissileTarget::None);
+        cmd.insert(event.kind);
+        cmd.insert(event.fuse);
         cmd.insert(PushableByExplosion);
+        if let Some(launcher) = event.launcher {
+            cmd.insert(LeavingLauncher(launcher));
+        }
+        if let MissileKind::Cluster {
+            split_after,
+            split_distance,
+            fragments,
+        } = event.kind
+        {
+            cmd.insert(ClusterSplit {
+                timer: Timer::from_seconds(split_after, TimerMode::Once),
+                split_distance,
+                fragments,
+            });
+        }
 
         cmd.insert((
             RigidBody::Dynamic,
             Collider::capsule_x(2.0, 0.25),
-            Velocity::linear(event.direction * 30.0),
+            initial_velocity,
             ActiveEvents::COLLISION_EVENTS,
             GravityScale(0.0),
+            SolverGroups {
+                memberships: solver_groups::MISSILE,
+                filters: Group::ALL,
+            },
         ));
     }
 }
 
+#[allow(clippy::type_complexity)]
+fn deflect_missiles(
+    players_query: Query<(&GlobalTransform, &DeflectState)>,
+    mut missiles_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &mut Transform,
+            &mut Velocity,
+            &mut MissileTarget,
+        ),
+        (With<MissileConfig>, Without<Deflected>),
+    >,
+    mut commands: Commands,
+) {
+    for (missile_entity, global_transform, mut transform, mut velocity, mut target) in
+        missiles_query.iter_mut()
+    {
+        let missile_position = global_transform.translation().truncate();
+        if !players_query
+            .iter()
+            .any(|(player_transform, deflect_state)| {
+                deflect_state.is_deflecting()
+                    && player_transform
+                        .translation()
+                        .truncate()
+                        .distance(missile_position)
+                        < DEFLECT_RADIUS
+            })
+        {
+            continue;
+        }
+        velocity.linvel = -velocity.linvel;
+        velocity.angvel = 0.0;
+        transform.rotation = Quat::from_rotation_z(std::f32::consts::PI) * transform.rotation;
+        *target = MissileTarget::None;
+        commands
+            .entity(missile_entity)
+            .insert(Deflected)
+            // A missile deflected right as it is fired should still be able to hit its cannon.
+            .remove::<LeavingLauncher>();
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn control_missiles(
     time: Res<Time>,
-    player_query: Query<&GlobalTransform, With<IsPlayer>>,
-    mut missiles_query: Query<(&MissileConfig, &mut Velocity, &GlobalTransform)>,
+    player_query: Query<(Entity, &GlobalTransform), With<IsPlayer>>,
+    enemies_query: Query<
+        (Entity, &GlobalTransform),
+        Or<(
+            With<CannonHitPoints>,
+            (With<MissileConfig>, Without<Deflected>),
+        )>,
+    >,
+    mut missiles_query: Query<(
+        Entity,
+        &MissileConfig,
+        &mut MissileTarget,
+        &mut Velocity,
+        &GlobalTransform,
+        Option<&ZoneEffects>,
+        Has<Deflected>,
+    )>,
 ) {
     if time.delta().is_zero() {
         return;
     }
-    for (missile_config, mut velocity, transform) in missiles_query.iter_mut() {
+    for (
+        missile_entity,
+        missile_config,
+        mut target,
+        mut velocity,
+        transform,
+        zone_effects,
+        deflected,
+    ) in missiles_query.iter_mut()
+    {
+        if zone_effects.is_some_and(|zone_effects| zone_effects.jammed) {
+            continue;
+        }
         let missile_position = transform.translation().truncate();
-        let Some(closest_player_position) = player_query
-            .iter()
-            .map(|t| t.translation().truncate())
-            .min_by_key(|player_position| {
-                OrderedFloat(player_position.distance_squared(missile_position))
-            })
-        else {
+        if let MissileTarget::LastSeenAt(position) = *target {
+            if REACQUIRE_TARGET_DISTANCE < position.distance(missile_position) {
+                missile_config.steer(
+                    missile_position,
+                    transform.right().truncate(),
+                    position,
+                    &mut velocity,
+                    time.delta_seconds(),
+                );
+                continue;
+            }
+        }
+        let target_position = if deflected {
+            *target = MissileTarget::None;
+            enemies_query
+                .iter()
+                .filter(|(enemy_entity, _)| *enemy_entity != missile_entity)
+                .map(|(_, enemy_transform)| enemy_transform.translation().truncate())
+                .min_by_key(|position| OrderedFloat(position.distance_squared(missile_position)))
+        } else {
+            let closest = player_query
+                .iter()
+                .map(|(player_entity, player_transform)| {
+                    (player_entity, player_transform.translation().truncate())
+                })
+                .min_by_key(|(_, position)| {
+                    OrderedFloat(position.distance_squared(missile_position))
+                });
+            *target = closest.map_or(MissileTarget::None, |(player_entity, _)| {
+                MissileTarget::Player(player_entity)
+            });
+            closest.map(|(_, position)| position)
+        };
+        let Some(target_position) = target_position else {
             continue;
         };
-        let vector_to_target = missile_position - closest_player_position;
-        let Some(direction_to_target) = vector_to_target.try_normalize() else {
+        missile_config.steer(
+            missile_position,
+            transform.right().truncate(),
+            target_position,
+            &mut velocity,
+            time.delta_seconds(),
+        );
+    }
+}
+
+fn split_cluster_missiles(
+    time: Res<Time>,
+    player_query: Query<&GlobalTransform, With<IsPlayer>>,
+    mut missiles_query: Query<
+        (
+            Entity,
+            &mut ClusterSplit,
+            &MissileFuse,
+            &GlobalTransform,
+            &YoleckBelongsToLevel,
+        ),
+        Without<Deflected>,
+    >,
+    mut commands: Commands,
+    mut writer: EventWriter<LaunchMissile>,
+) {
+    for (missile_entity, mut cluster_split, fuse, transform, belongs_to_level) in
+        missiles_query.iter_mut()
+    {
+        let position = transform.translation().truncate();
+        let timer_finished = cluster_split.timer.tick(time.delta()).finished();
+        let near_player = player_query.iter().any(|player_transform| {
+            player_transform.translation().truncate().distance(position)
+                < cluster_split.split_distance
+        });
+        if !timer_finished && !near_player {
             continue;
+        }
+        commands.entity(missile_entity).despawn_recursive();
+        let heading = transform.right().truncate().normalize_or_zero();
+        for i in 0..cluster_split.fragments {
+            let angle = if cluster_split.fragments <= 1 {
+                0.0
+            } else {
+                CLUSTER_SPREAD * (i as f32 / (cluster_split.fragments - 1) as f32 - 0.5)
+            };
+            let direction = Vec2::from_angle(angle).rotate(heading);
+            writer.send(LaunchMissile {
+                level: belongs_to_level.level,
+                // Spread them a bit, so that they won't all spawn inside each other.
+                position: position + direction,
+                direction,
+                kind: MissileKind::Small,
+                fuse: *fuse,
+                launcher: None,
+            });
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn light_proximity_fuses(
+    player_query: Query<&GlobalTransform, With<IsPlayer>>,
+    missiles_query: Query<
+        (Entity, &MissileFuse, &GlobalTransform),
+        (Without<BurningFuse>, Without<Deflected>),
+    >,
+    mut commands: Commands,
+) {
+    for (missile_entity, fuse, transform) in missiles_query.iter() {
+        let Some(proximity) = fuse.proximity else {
+            continue;
+        };
+        let position = transform.translation().truncate();
+        if player_query.iter().any(|player_transform| {
+            player_transform.translation().truncate().distance(position) < proximity
+        }) {
+            commands
+                .entity(missile_entity)
+                .insert(BurningFuse(Timer::from_seconds(
+                    fuse.delay,
+                    TimerMode::Once,
+                )));
+        }
+    }
+}
+
+fn burn_fuses(
+    time: Res<Time>,
+    mut missiles_query: Query<(
+        Entity,
+        &mut BurningFuse,
+        &MissileKind,
+        &GlobalTransform,
+        &YoleckBelongsToLevel,
+    )>,
+    mut commands: Commands,
+    mut explosion_writer: EventWriter<StartExplosion>,
+) {
+    for (missile_entity, mut burning_fuse, missile_kind, transform, belongs_to_level) in
+        missiles_query.iter_mut()
+    {
+        if burning_fuse.0.tick(time.delta()).finished() {
+            commands.entity(missile_entity).despawn_recursive();
+            explosion_writer.send(StartExplosion {
+                level: belongs_to_level.level,
+                position: transform.translation().truncate(),
+                config: missile_kind.explosion(),
+            });
+        }
+    }
+}
+
+/// Shows where a missile with a lit fuse is about to explode, so that the players can get away.
+fn flash_burning_fuses(
+    query: Query<(&BurningFuse, &MissileKind, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    for (burning_fuse, missile_kind, transform) in query.iter() {
+        let position = transform.translation().truncate();
+        gizmos.circle_2d(position, missile_kind.explosion().radius, Color::ORANGE_RED);
+        if (burning_fuse.0.elapsed_secs() * FUSE_FLASH_FREQUENCY).fract() < 0.5 {
+            gizmos.circle_2d(position, 1.0, Color::YELLOW);
+            gizmos.circle_2d(position, 1.2, Color::YELLOW);
+        }
+    }
+}
+
+fn closest_target(position: Vec2, targets: impl Iterator<Item = Vec2>) -> Option<Vec2> {
+    targets.min_by_key(|target| OrderedFloat(target.distance_squared(position)))
+}
+
+impl MissileConfig {
+    /// Turn and accelerate a missile heading in `heading` so that it'll home in on `target`.
+    fn steer(
+        &self,
+        position: Vec2,
+        heading: Vec2,
+        target: Vec2,
+        velocity: &mut Velocity,
+        delta_seconds: f32,
+    ) {
+        let vector_to_target = position - target;
+        let Some(direction_to_target) = vector_to_target.try_normalize() else {
+            return;
         };
-        let angle_diff = -transform
-            .right()
-            .truncate()
-            .angle_between(direction_to_target);
-        let desired_angvel = (angle_diff / time.delta_seconds())
-            .clamp(-missile_config.angular_speed, missile_config.angular_speed);
+        let angle_diff = -heading.angle_between(direction_to_target);
+        let desired_angvel =
+            (angle_diff / delta_seconds).clamp(-self.angular_speed, self.angular_speed);
         let angular_velocity_diff = desired_angvel - velocity.angvel;
-        let maximum_impulse = missile_config.angular_acceleration * time.delta_seconds();
+        let maximum_impulse = self.angular_acceleration * delta_seconds;
         let angular_impulse = angular_velocity_diff.clamp(-maximum_impulse, maximum_impulse);
         velocity.angvel += angular_impulse;
 
-        let direction = transform.right().truncate();
-        let current_speed = velocity.linvel.dot(direction);
-        let additional_speed_required = missile_config.speed - current_speed;
+        let current_speed = velocity.linvel.dot(heading);
+        let additional_speed_required = self.speed - current_speed;
         if 0.0 < additional_speed_required {
             let homing_ratio = angle_diff.abs() / std::f32::consts::PI;
             if 0.8 < homing_ratio {
-                let boost = additional_speed_required
-                    .min(homing_ratio * missile_config.acceleration * time.delta_seconds());
-                velocity.linvel += boost * direction;
+                let boost =
+                    additional_speed_required.min(homing_ratio * self.acceleration * delta_seconds);
+                velocity.linvel += boost * heading;
+            }
+        }
+    }
+}
+
+/// Predict the path of a missile launched like [`LaunchMissile`] while it chases the closest of the
+/// `targets`, which are assumed to stay in place. Stops early if the missile reaches a target.
+pub fn simulate_missile_path(
+    missile_config: &MissileConfig,
+    position: Vec2,
+    direction: Vec2,
+    targets: &[Vec2],
+    duration: f32,
+    time_step: f32,
+) -> Vec<Vec2> {
+    let mut position = position;
+    let mut angle = Vec2::X.angle_between(direction);
+    let mut velocity = Velocity::linear(direction * missile_config.speed);
+    let mut path = vec![position];
+    for _ in 0..(duration / time_step) as usize {
+        let heading = Vec2::from_angle(angle);
+        if let Some(target) = closest_target(position, targets.iter().copied()) {
+            if target.distance(position) < 1.0 {
+                break;
             }
+            missile_config.steer(position, heading, target, &mut velocity, time_step);
         }
+        position += velocity.linvel * time_step;
+        angle += velocity.angvel * time_step;
+        path.push(position);
     }
+    path
 }
 
+#[allow(clippy::type_complexity)]
 fn explode_missiles_on_impact(
     mut reader: EventReader<CollisionEvent>,
-    missile_query: Query<(&GlobalTransform, &YoleckBelongsToLevel), With<MissileConfig>>,
+    missile_query: Query<
+        (
+            &GlobalTransform,
+            &YoleckBelongsToLevel,
+            &MissileKind,
+            Option<&LeavingLauncher>,
+            Has<Deflected>,
+        ),
+        With<MissileConfig>,
+    >,
     other_object_query: Query<(), With<ExplodesMissileOnImpact>>,
     mut commands: Commands,
     mut explosion_writer: EventWriter<StartExplosion>,
 ) {
-    for (e1, e2) in collision_started_events_both_ways(&mut reader) {
-        if !other_object_query.contains(e2) {
-            continue;
-        }
-        let Ok((transform, belongs_to_level)) = missile_query.get(e1) else {
-            continue;
+    for event in reader.read() {
+        let (e1, e2, started) = match event {
+            CollisionEvent::Started(e1, e2, _) => (*e1, *e2, true),
+            CollisionEvent::Stopped(e1, e2, _) => (*e1, *e2, false),
         };
-        commands.entity(e1).despawn_recursive();
-        explosion_writer.send(StartExplosion {
-            level: belongs_to_level.level,
-            position: transform.translation().truncate(),
-        })
+        for (missile, other) in [(e1, e2), (e2, e1)] {
+            let Ok((transform, belongs_to_level, missile_kind, leaving_launcher, deflected)) =
+                missile_query.get(missile)
+            else {
+                continue;
+            };
+            if leaving_launcher.is_some_and(|leaving_launcher| leaving_launcher.0 == other) {
+                if !started {
+                    commands.entity(missile).remove::<LeavingLauncher>();
+                }
+                continue;
+            }
+            // Deflected missiles take out the missiles they hit, and get taken out by them.
+            let hit_missile_with_deflected = missile_query
+                .get(other)
+                .is_ok_and(|(_, _, _, _, other_deflected)| deflected || other_deflected);
+            if !started || !(other_object_query.contains(other) || hit_missile_with_deflected) {
+                continue;
+            }
+            commands.entity(missile).despawn_recursive();
+            explosion_writer.send(StartExplosion {
+                level: belongs_to_level.level,
+                position: transform.translation().truncate(),
+                config: missile_kind.explosion(),
+            })
+        }
     }
 }
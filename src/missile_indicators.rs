@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier2d::prelude::*;
+use ordered_float::OrderedFloat;
+
+use crate::missile::MissileConfig;
+use crate::player::IsPlayer;
+use crate::During;
+
+pub struct MissileIndicatorsPlugin;
+
+impl Plugin for MissileIndicatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            draw_offscreen_missile_indicators.in_set(During::Gameplay),
+        );
+    }
+}
+
+/// Distance of the indicators from the edge of the screen.
+const INDICATORS_MARGIN: f32 = 20.0;
+
+const INDICATOR_SIZE: f32 = 16.0;
+
+/// Missiles that will hit later than this get the calmest indicator color.
+const MAX_INDICATED_TIME_TO_IMPACT: f32 = 3.0;
+
+fn draw_offscreen_missile_indicators(
+    mut egui_contexts: EguiContexts,
+    cameras_query: Query<(&Camera, &GlobalTransform)>,
+    players_query: Query<&GlobalTransform, With<IsPlayer>>,
+    missiles_query: Query<(&GlobalTransform, &Velocity), With<MissileConfig>>,
+) {
+    let painter = egui_contexts.ctx_mut().layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("offscreen-missile-indicators"),
+    ));
+    for (camera, camera_transform) in cameras_query.iter() {
+        if !camera.is_active {
+            continue;
+        }
+        let Some(viewport_rect) = camera.logical_viewport_rect() else {
+            continue;
+        };
+        let indicators_half_size = 0.5 * viewport_rect.size() - INDICATORS_MARGIN;
+        for (missile_transform, missile_velocity) in missiles_query.iter() {
+            let missile_position = missile_transform.translation();
+            let Some(time_to_impact) = players_query
+                .iter()
+                .filter_map(|player_transform| {
+                    let vector_to_player =
+                        (player_transform.translation() - missile_position).truncate();
+                    let closing_speed = missile_velocity
+                        .linvel
+                        .dot(vector_to_player.normalize_or_zero());
+                    (0.0 < closing_speed).then(|| vector_to_player.length() / closing_speed)
+                })
+                .min_by_key(|time_to_impact| OrderedFloat(*time_to_impact))
+            else {
+                continue;
+            };
+            let Some(position_in_viewport) =
+                camera.world_to_viewport(camera_transform, missile_position)
+            else {
+                continue;
+            };
+            let position_on_screen = viewport_rect.min + position_in_viewport;
+            if viewport_rect.contains(position_on_screen) {
+                continue;
+            }
+
+            let Some(direction) = (position_on_screen - viewport_rect.center()).try_normalize()
+            else {
+                continue;
+            };
+            let distance_to_edge = (indicators_half_size / direction.abs()).min_element();
+            let tip = viewport_rect.center() + distance_to_edge * direction;
+            let base = tip - INDICATOR_SIZE * direction;
+            let side = 0.5 * INDICATOR_SIZE * direction.perp();
+
+            let urgency = 1.0 - (time_to_impact / MAX_INDICATED_TIME_TO_IMPACT).clamp(0.0, 1.0);
+            let color = egui::Color32::from_rgb(255, (255.0 * (1.0 - urgency)) as u8, 0);
+            painter.add(egui::Shape::convex_polygon(
+                [tip, base + side, base - side]
+                    .into_iter()
+                    .map(|point| egui::Pos2::new(point.x, point.y))
+                    .collect(),
+                color,
+                egui::Stroke::NONE,
+            ));
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_rapier2d::prelude::*;
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dRotatation, Vpeol3dScale};
+use serde::{Deserialize, Serialize};
+
+use crate::arena::{populate_block, BlockSurface, IsBlock};
+use crate::missile::ExplodesMissileOnImpact;
+use crate::utils::CachedPbrMaker;
+use crate::During;
+
+pub struct MovingBlockPlugin;
+
+impl Plugin for MovingBlockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("MovingBlock")
+                .with::<Vpeol3dPosition>()
+                .with::<Vpeol3dScale>()
+                .with::<Vpeol3dRotatation>()
+                .with::<BlockSurface>()
+                .with::<MovingBlockPath>()
+                .insert_on_init(|| (IsBlock, ExplodesMissileOnImpact))
+        });
+
+        app.add_yoleck_edit_system(edit_moving_block_path);
+
+        app.add_systems(
+            YoleckSchedule::Populate,
+            populate_moving_block.after(populate_block),
+        );
+        app.add_systems(Update, move_blocks_along_paths.in_set(During::Gameplay));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PathEasing {
+    Linear,
+    /// Slow down near each waypoint.
+    EaseInOut,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PathMode {
+    /// Go back and forth between the first and the last waypoints.
+    PingPong,
+    /// Go back from the last waypoint directly to the first one.
+    Loop,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct MovingBlockPath {
+    /// The waypoints the block moves through after its starting position, relative to it.
+    pub waypoints: Vec<Vec2>,
+    pub speed: f32,
+    pub easing: PathEasing,
+    pub mode: PathMode,
+}
+
+impl Default for MovingBlockPath {
+    fn default() -> Self {
+        Self {
+            waypoints: vec![Vec2::new(5.0, 0.0)],
+            speed: 5.0,
+            easing: PathEasing::Linear,
+            mode: PathMode::PingPong,
+        }
+    }
+}
+
+impl MovingBlockPath {
+    /// The points the block moves between during a single cycle, relative to its start.
+    fn cycle_points(&self) -> Vec<Vec2> {
+        let mut points = vec![Vec2::ZERO];
+        points.extend(self.waypoints.iter().copied());
+        match self.mode {
+            PathMode::PingPong => {
+                points.extend(self.waypoints.iter().rev().skip(1).copied());
+                points.push(Vec2::ZERO);
+            }
+            PathMode::Loop => {
+                points.push(Vec2::ZERO);
+            }
+        }
+        points
+    }
+
+    /// Where the block should be, relative to its start, after moving for `time` seconds.
+    fn offset_at(&self, time: f32) -> Vec2 {
+        let points = self.cycle_points();
+        let cycle_length = points
+            .windows(2)
+            .map(|segment| segment[0].distance(segment[1]))
+            .sum::<f32>();
+        if cycle_length <= 0.0 || self.speed <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let mut distance = (time * self.speed).rem_euclid(cycle_length);
+        for segment in points.windows(2) {
+            let segment_length = segment[0].distance(segment[1]);
+            if distance < segment_length {
+                let t = distance / segment_length;
+                let t = match self.easing {
+                    PathEasing::Linear => t,
+                    PathEasing::EaseInOut => t * t * (3.0 - 2.0 * t),
+                };
+                return segment[0].lerp(segment[1], t);
+            }
+            distance -= segment_length;
+        }
+        Vec2::ZERO
+    }
+}
+
+#[derive(Component)]
+struct MovingBlockTime(f32);
+
+fn populate_moving_block(mut populate: YoleckPopulate<(), With<MovingBlockPath>>) {
+    populate.populate(|ctx, mut cmd, ()| {
+        if ctx.is_in_editor() {
+            return;
+        }
+        cmd.insert(RigidBody::KinematicVelocityBased);
+        cmd.insert(Velocity::default());
+        cmd.insert(MovingBlockTime(0.0));
+    });
+}
+
+fn edit_moving_block_path(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<(Entity, &mut MovingBlockPath, &Vpeol3dPosition)>,
+    mut knobs: YoleckKnobs,
+    mut pbr: CachedPbrMaker,
+    mut gizmos: Gizmos,
+) {
+    let Ok((entity, mut path, position)) = edit.get_single_mut() else {
+        return;
+    };
+
+    ui.add(egui::Slider::new(&mut path.speed, 0.5..=30.0).prefix("Speed: "));
+    ui.horizontal(|ui| {
+        ui.label("Easing:");
+        ui.selectable_value(&mut path.easing, PathEasing::Linear, "Linear");
+        ui.selectable_value(&mut path.easing, PathEasing::EaseInOut, "Ease In/Out");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Mode:");
+        ui.selectable_value(&mut path.mode, PathMode::PingPong, "Ping-Pong");
+        ui.selectable_value(&mut path.mode, PathMode::Loop, "Loop");
+    });
+    ui.horizontal(|ui| {
+        if ui.button("Add Waypoint").clicked() {
+            let last = path.waypoints.last().copied().unwrap_or(Vec2::ZERO);
+            path.waypoints.push(last + Vec2::new(5.0, 0.0));
+        }
+        if 1 < path.waypoints.len() && ui.button("Remove Last Waypoint").clicked() {
+            path.waypoints.pop();
+        }
+    });
+
+    for (i, waypoint) in path.waypoints.iter_mut().enumerate() {
+        let mut knob = knobs.knob(("moving-block-waypoint", entity, i));
+        if knob.is_new {
+            knob.cmd.insert(pbr.make_pbr_with(
+                || {
+                    Mesh::from(shape::UVSphere {
+                        radius: 0.4,
+                        sectors: 10,
+                        stacks: 10,
+                    })
+                },
+                || Color::YELLOW.into(),
+            ));
+        }
+        knob.cmd.insert(Transform::from_translation(
+            position.0 + waypoint.extend(0.0),
+        ));
+        if let Some(new_knob_pos) = knob.get_passed_data::<Vec3>() {
+            *waypoint = (*new_knob_pos - position.0).truncate();
+        }
+    }
+
+    gizmos.linestrip(
+        path.cycle_points()
+            .into_iter()
+            .map(|point| position.0 + point.extend(0.0)),
+        Color::YELLOW,
+    );
+}
+
+fn move_blocks_along_paths(
+    time: Res<Time>,
+    mut query: Query<(
+        &MovingBlockPath,
+        &Vpeol3dPosition,
+        &mut MovingBlockTime,
+        &GlobalTransform,
+        &mut Velocity,
+    )>,
+) {
+    if time.delta().is_zero() {
+        return;
+    }
+    for (path, start, mut block_time, transform, mut velocity) in query.iter_mut() {
+        block_time.0 += time.delta_seconds();
+        let target = start.0.truncate() + path.offset_at(block_time.0);
+        // Moving by velocity (rather than teleporting) lets Tnua carry the players standing on
+        // the block.
+        velocity.linvel = (target - transform.translation().truncate()) / time.delta_seconds();
+    }
+}
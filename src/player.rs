@@ -8,12 +8,12 @@ use bevy_tnua_rapier2d::{TnuaRapier2dIOBundle, TnuaRapier2dSensorShape};
 use bevy_yoleck::prelude::*;
 use bevy_yoleck::vpeol::VpeolWillContainClickableChildren;
 use bevy_yoleck::vpeol_3d::Vpeol3dPosition;
-use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
 
 use crate::animating::{AnimationsOwner, GetClipsFrom};
-use crate::arena::IsBlock;
 use crate::explosion::PushableByExplosion;
-use crate::{AppState, During};
+use crate::player_controls::DeflectState;
+use crate::During;
 
 pub struct PlayerPlugin;
 
@@ -22,20 +22,44 @@ impl Plugin for PlayerPlugin {
         app.add_yoleck_entity_type({
             YoleckEntityType::new("Player")
                 .with::<Vpeol3dPosition>()
+                .with::<PlayerSlot>()
                 .insert_on_init(|| IsPlayer)
         });
+        app.init_resource::<NumberOfPlayers>();
         app.add_systems(YoleckSchedule::Populate, populate_player);
+        app.add_yoleck_edit_system(edit_player_slot);
+        app.add_systems(Update, despawn_unused_player_slots);
         app.add_systems(
             Update,
             (set_player_facing, animate_player).in_set(During::Gameplay),
         );
-        app.add_systems(Update, kill_player_when_they_fall.in_set(During::Gameplay));
     }
 }
 
 #[derive(Component)]
 pub struct IsPlayer;
 
+pub const MAX_PLAYERS: usize = 4;
+
+/// Which of the local co-op players spawns at this entity.
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct PlayerSlot {
+    pub index: usize,
+}
+
+#[derive(Resource)]
+pub struct NumberOfPlayers(pub usize);
+
+impl Default for NumberOfPlayers {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// Marks player spawns whose slot is not used in the current game.
+#[derive(Component)]
+pub struct UnusedPlayerSlot;
+
 #[derive(Component, Debug)]
 pub enum PlayerFacing {
     Left,
@@ -55,10 +79,14 @@ impl PlayerFacing {
 struct RotationBasedOn(Entity);
 
 fn populate_player(
-    mut populate: YoleckPopulate<(), With<IsPlayer>>,
+    mut populate: YoleckPopulate<&PlayerSlot, With<IsPlayer>>,
     asset_server: Res<AssetServer>,
+    number_of_players: Res<NumberOfPlayers>,
 ) {
-    populate.populate(|ctx, mut cmd, ()| {
+    populate.populate(|ctx, mut cmd, player_slot| {
+        if !ctx.is_in_editor() && number_of_players.0 <= player_slot.index {
+            cmd.insert(UnusedPlayerSlot);
+        }
         if ctx.is_first_time() {
             cmd.insert(VpeolWillContainClickableChildren);
             let rotation_based_on = RotationBasedOn(cmd.id());
@@ -87,10 +115,10 @@ fn populate_player(
         cmd.insert(LockedAxes::ROTATION_LOCKED);
         cmd.insert(TnuaRapier2dSensorShape(Collider::cuboid(0.45, 0.0)));
         cmd.insert(ActiveEvents::COLLISION_EVENTS);
-        // cmd.insert(SolverGroups {
-        // memberships: crate::solver_groups::PLAYER,
-        // filters: crate::solver_groups::PLANTED,
-        // });
+        cmd.insert(SolverGroups {
+            memberships: crate::solver_groups::PLAYER,
+            filters: Group::ALL,
+        });
 
         cmd.insert(PlayerFacing::Right);
         cmd.insert(PushableByExplosion);
@@ -100,6 +128,30 @@ fn populate_player(
     });
 }
 
+fn edit_player_slot(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut PlayerSlot, With<IsPlayer>>,
+) {
+    let Ok(mut player_slot) = edit.get_single_mut() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        ui.label("Player:");
+        for index in 0..MAX_PLAYERS {
+            ui.selectable_value(&mut player_slot.index, index, format!("{}", index + 1));
+        }
+    });
+}
+
+fn despawn_unused_player_slots(
+    query: Query<Entity, With<UnusedPlayerSlot>>,
+    mut commands: Commands,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 fn set_player_facing(
     mut query: Query<(&mut Transform, &RotationBasedOn)>,
     players_query: Query<&PlayerFacing>,
@@ -119,17 +171,19 @@ pub enum PlayerAnimationState {
     Jumping,
     AirJumping,
     Dashing,
+    Deflecting,
 }
 
 fn animate_player(
     mut query: Query<(
         &mut TnuaAnimatingState<PlayerAnimationState>,
         &TnuaController,
+        Option<&DeflectState>,
         &AnimationsOwner,
     )>,
     mut animation_players_query: Query<&mut AnimationPlayer>,
 ) {
-    for (mut animating_state, controller, animations_owner) in query.iter_mut() {
+    for (mut animating_state, controller, deflect_state, animations_owner) in query.iter_mut() {
         let Some(animation_player) = animations_owner.players.get("Armature") else {
             continue;
         };
@@ -137,10 +191,13 @@ fn animate_player(
             continue;
         };
         match animating_state.update_by_discriminant({
+            let deflecting =
+                deflect_state.is_some_and(|deflect_state| deflect_state.is_deflecting());
             match controller.action_name() {
+                Some(TnuaBuiltinDash::NAME) => PlayerAnimationState::Dashing,
+                _ if deflecting => PlayerAnimationState::Deflecting,
                 Some(TnuaBuiltinJump::NAME) => PlayerAnimationState::Jumping,
                 Some("air-jump") => PlayerAnimationState::AirJumping,
-                Some(TnuaBuiltinDash::NAME) => PlayerAnimationState::Dashing,
                 Some(name) => panic!("Unknown action {name}"),
                 None => {
                     let Some((_, walk_state)) = controller.concrete_basis::<TnuaBuiltinWalk>()
@@ -200,27 +257,18 @@ fn animate_player(
                     };
                     animation_player.play(clip.clone());
                 }
+                PlayerAnimationState::Deflecting => {
+                    // The model has no dedicated deflect animation yet, so a quick dash is used.
+                    let Some(clip) = animations_owner
+                        .clips
+                        .get("Deflect")
+                        .or_else(|| animations_owner.clips.get("Dash"))
+                    else {
+                        continue;
+                    };
+                    animation_player.play(clip.clone()).set_speed(2.0);
+                }
             },
         }
     }
 }
-
-fn kill_player_when_they_fall(
-    objects_query: Query<&GlobalTransform, With<IsBlock>>,
-    players_query: Query<&GlobalTransform, With<IsPlayer>>,
-    mut app_state: ResMut<NextState<AppState>>,
-) {
-    let Some(lowest_y) = objects_query
-        .iter()
-        .map(|transform| transform.transform_point(0.5 * Vec3::Y).y)
-        .min_by_key(|y| OrderedFloat(*y))
-    else {
-        return;
-    };
-    for player_transform in players_query.iter() {
-        let player_below_level = player_transform.translation().y - lowest_y;
-        if player_below_level < -50.0 {
-            app_state.set(AppState::GameOver);
-        }
-    }
-}
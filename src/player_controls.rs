@@ -1,28 +1,52 @@
 use std::time::Duration;
 
+use bevy::input::gamepad::GamepadConnectionEvent;
 use bevy::prelude::*;
 use bevy_tnua::builtins::TnuaBuiltinDash;
-use bevy_tnua::control_helpers::TnuaAirActionsTracker;
+use bevy_tnua::control_helpers::{TnuaAirActionsTracker, TnuaSimpleFallThroughPlatformsHelper};
+use bevy_tnua::controller::TnuaActionFlowStatus;
 use bevy_tnua::prelude::*;
+use bevy_tnua::{TnuaBasis, TnuaGhostSensor, TnuaProximitySensor};
 use bevy_yoleck::prelude::*;
 use leafwing_input_manager::prelude::*;
 
-use crate::player::{IsPlayer, PlayerFacing};
+use crate::arena::BlockSurface;
+use crate::force_zone::ZoneEffects;
+use crate::player::{IsPlayer, PlayerFacing, PlayerSlot, UnusedPlayerSlot};
 use crate::During;
 
 #[derive(Actionlike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect)]
 enum PlayerAction {
     Run,
     Jump,
+    Deflect,
 }
 
 pub struct PlayerControlsPlugin;
 
+pub const RUN_SPEED: f32 = 20.0;
+pub const FLOAT_HEIGHT: f32 = 1.5;
+pub const JUMP_HEIGHT: f32 = 5.0;
+pub const AIR_JUMP_HEIGHT: f32 = 4.0;
+pub const DASH_DISTANCE: f32 = 10.0;
+/// How close a missile needs to be to a deflecting player to get deflected.
+pub const DEFLECT_RADIUS: f32 = 3.0;
+const DEFLECT_WINDOW: f32 = 0.25;
+const DEFLECT_COOLDOWN: f32 = 0.5;
+
 impl Plugin for PlayerControlsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(InputManagerPlugin::<PlayerAction>::default());
+        app.init_resource::<JumpAssistSettings>();
         app.add_systems(YoleckSchedule::Populate, add_controls_to_player);
-        app.add_systems(Update, apply_controls.in_set(During::Gameplay));
+        app.add_systems(Update, assign_input_maps);
+        app.add_systems(
+            Update,
+            apply_controls
+                .in_set(During::Gameplay)
+                .in_set(TnuaUserControlsSystemSet),
+        );
+        app.add_systems(Update, draw_deflect_windows.in_set(During::Gameplay));
     }
 }
 
@@ -31,26 +55,102 @@ fn add_controls_to_player(mut populate: YoleckPopulate<(), With<IsPlayer>>) {
         if ctx.is_in_editor() {
             return;
         }
-        cmd.insert(InputManagerBundle::<PlayerAction> {
-            action_state: Default::default(),
-            input_map: {
-                let mut input_map = InputMap::default();
+        cmd.insert(InputManagerBundle::<PlayerAction>::default());
+        cmd.insert(PlayerAirCounters::default());
+        cmd.insert(DeflectState::default());
+        cmd.insert(JumpInputBuffer::default());
+        cmd.insert(DoubleClickInputs::default());
+        cmd.insert(TnuaGhostSensor::default());
+        cmd.insert(TnuaSimpleFallThroughPlatformsHelper::default());
+        cmd.insert(DroppingThroughPlatforms(false));
+    });
+}
 
-                input_map.insert(VirtualDPad::arrow_keys(), PlayerAction::Run);
-                input_map.insert(VirtualDPad::wasd(), PlayerAction::Run);
-                input_map.insert(VirtualDPad::dpad(), PlayerAction::Run);
-                input_map.insert(DualAxis::left_stick(), PlayerAction::Run);
+/// Splits the input devices between the players. A single player gets all of them. With multiple
+/// players, the first two split the keyboard between them, and each player gets its own gamepad
+/// out of the connected ones. The gamepads are reassigned whenever one connects or disconnects.
+fn assign_input_maps(
+    mut query: Query<(&PlayerSlot, &mut InputMap<PlayerAction>), Without<UnusedPlayerSlot>>,
+    added_query: Query<(), Added<InputMap<PlayerAction>>>,
+    mut gamepad_connection_events: EventReader<GamepadConnectionEvent>,
+    gamepads: Res<Gamepads>,
+) {
+    let gamepads_changed = gamepad_connection_events.read().count() != 0;
+    if added_query.is_empty() && !gamepads_changed {
+        return;
+    }
+    let mut gamepads = gamepads.iter().collect::<Vec<_>>();
+    gamepads.sort_by_key(|gamepad| gamepad.id);
+    let mut players = query.iter_mut().collect::<Vec<_>>();
+    players.sort_by_key(|(player_slot, _)| player_slot.index);
+    let num_players = players.len();
+    for (rank, (_, mut input_map)) in players.into_iter().enumerate() {
+        *input_map = input_map_for_player(rank, num_players, gamepads.get(rank).copied());
+    }
+}
+
+/// `gamepad` is only used with multiple players - a single player uses whichever is connected.
+fn input_map_for_player(
+    rank: usize,
+    num_players: usize,
+    gamepad: Option<Gamepad>,
+) -> InputMap<PlayerAction> {
+    let mut input_map = InputMap::default();
 
+    if num_players == 1 {
+        input_map.insert(VirtualDPad::arrow_keys(), PlayerAction::Run);
+        input_map.insert(VirtualDPad::wasd(), PlayerAction::Run);
+        input_map.insert(KeyCode::Space, PlayerAction::Jump);
+        input_map.insert(KeyCode::J, PlayerAction::Jump);
+        input_map.insert(KeyCode::K, PlayerAction::Deflect);
+        input_map.insert(KeyCode::ShiftLeft, PlayerAction::Deflect);
+    } else {
+        match rank {
+            0 => {
+                input_map.insert(VirtualDPad::wasd(), PlayerAction::Run);
                 input_map.insert(KeyCode::Space, PlayerAction::Jump);
-                input_map.insert(KeyCode::J, PlayerAction::Jump);
-                input_map.insert(GamepadButtonType::South, PlayerAction::Jump);
+                input_map.insert(KeyCode::ShiftLeft, PlayerAction::Deflect);
+            }
+            1 => {
+                input_map.insert(VirtualDPad::arrow_keys(), PlayerAction::Run);
+                input_map.insert(KeyCode::ShiftRight, PlayerAction::Jump);
+                input_map.insert(KeyCode::Return, PlayerAction::Jump);
+                input_map.insert(KeyCode::ControlRight, PlayerAction::Deflect);
+            }
+            _ => {}
+        }
+        // An input map without a gamepad reads the first connected one, so a player that did not
+        // get a gamepad of their own must not have gamepad bindings at all.
+        let Some(gamepad) = gamepad else {
+            return input_map;
+        };
+        input_map.set_gamepad(gamepad);
+    }
 
-                input_map
-            },
-        });
-        cmd.insert(PlayerAirCounters::default());
-        cmd.insert(DoubleClickInputs::default());
-    });
+    input_map.insert(VirtualDPad::dpad(), PlayerAction::Run);
+    input_map.insert(DualAxis::left_stick(), PlayerAction::Run);
+    input_map.insert(GamepadButtonType::South, PlayerAction::Jump);
+    input_map.insert(GamepadButtonType::West, PlayerAction::Deflect);
+
+    input_map
+}
+
+/// Timings that make jumps more forgiving.
+#[derive(Resource)]
+pub struct JumpAssistSettings {
+    /// How long, in seconds, a jump press is remembered before the jump becomes possible.
+    pub buffer_time: f32,
+    /// How long, in seconds, after walking off a ledge the player can still do a ground jump.
+    pub coyote_time: f32,
+}
+
+impl Default for JumpAssistSettings {
+    fn default() -> Self {
+        Self {
+            buffer_time: 0.15,
+            coyote_time: 0.15,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -67,6 +167,8 @@ struct PlayerAirCounters {
     current: CurrentAirAction,
     jumps: usize,
     dashes: usize,
+    /// Deflects started with the deflect input. Deflects that come with a dash are not counted.
+    deflects: usize,
 }
 
 impl PlayerAirCounters {
@@ -96,6 +198,7 @@ impl PlayerAirCounters {
                 self.current = CurrentAirAction::None;
                 self.jumps = 0;
                 self.dashes = 0;
+                self.deflects = 0;
             }
         }
     }
@@ -117,6 +220,86 @@ impl PlayerAirCounters {
     }
 }
 
+/// The short window in which the player deflects the missiles that get near it.
+#[derive(Component, Default)]
+pub struct DeflectState {
+    window: Option<Timer>,
+    cooldown: Option<Timer>,
+}
+
+impl DeflectState {
+    fn update(&mut self, time_delta: Duration) {
+        if let Some(timer) = self.window.as_mut() {
+            if timer.tick(time_delta).finished() {
+                self.window = None;
+                self.cooldown = Some(Timer::from_seconds(DEFLECT_COOLDOWN, TimerMode::Once));
+            }
+        }
+        if let Some(timer) = self.cooldown.as_mut() {
+            if timer.tick(time_delta).finished() {
+                self.cooldown = None;
+            }
+        }
+    }
+
+    /// Returns `false` if the player is already deflecting or has just deflected.
+    fn try_start(&mut self) -> bool {
+        if self.window.is_some() || self.cooldown.is_some() {
+            return false;
+        }
+        self.window = Some(Timer::from_seconds(DEFLECT_WINDOW, TimerMode::Once));
+        true
+    }
+
+    pub fn is_deflecting(&self) -> bool {
+        self.window.is_some()
+    }
+}
+
+/// Remembers a jump press for a short while, so that pressing jump right before landing (or
+/// before an air jump becomes available) still results in a jump.
+///
+/// This is the only jump buffer - the jump actions themselves don't use Tnua's input buffering.
+/// The buffered jump is only fed to the controller once it is possible, and then kept at full
+/// height until it is over, since by then the button was usually already released.
+#[derive(Component, Default)]
+struct JumpInputBuffer {
+    pending: Option<Timer>,
+    buffered_jump_running: bool,
+}
+
+impl JumpInputBuffer {
+    fn update(&mut self, controller: &TnuaController, time_delta: Duration) {
+        if self.buffered_jump_running && !is_jumping(controller) {
+            self.buffered_jump_running = false;
+        }
+        if let Some(timer) = self.pending.as_mut() {
+            if timer.tick(time_delta).finished() {
+                self.pending = None;
+            }
+        }
+    }
+
+    fn press(&mut self, buffer_time: f32) {
+        self.pending = Some(Timer::from_seconds(buffer_time, TimerMode::Once));
+    }
+
+    /// Whether a buffered press should be jumping right now.
+    fn should_jump(&mut self, jump_possible: bool) -> bool {
+        if jump_possible && self.pending.take().is_some() {
+            self.buffered_jump_running = true;
+        }
+        self.buffered_jump_running
+    }
+}
+
+fn is_jumping(controller: &TnuaController) -> bool {
+    matches!(
+        controller.action_name(),
+        Some(TnuaBuiltinJump::NAME | "air-jump")
+    )
+}
+
 #[derive(Default)]
 enum DoubleClickDetector {
     #[default]
@@ -188,59 +371,158 @@ impl DoubleClickInputs {
     }
 }
 
+/// Set when the player presses down+jump on a one-way platform, and stays set while down is held.
+#[derive(Component)]
+struct DroppingThroughPlatforms(bool);
+
+/// The character's bottom must be above a one-way platform to stand on it.
+const MIN_PLATFORM_PROXIMITY: f32 = 0.5;
+
+#[allow(clippy::type_complexity)]
 fn apply_controls(
     time: Res<Time>,
+    jump_assist_settings: Res<JumpAssistSettings>,
     mut query: Query<(
         &ActionState<PlayerAction>,
         &mut TnuaController,
         &mut PlayerFacing,
         &mut PlayerAirCounters,
+        &mut DeflectState,
+        &mut JumpInputBuffer,
         &mut DoubleClickInputs,
+        &mut TnuaProximitySensor,
+        &TnuaGhostSensor,
+        &mut TnuaSimpleFallThroughPlatformsHelper,
+        &mut DroppingThroughPlatforms,
+        Option<&ZoneEffects>,
     )>,
+    surfaces_query: Query<(&BlockSurface, &GlobalTransform)>,
 ) {
-    for (input, mut controller, mut player_facing, mut air_counters, mut double_click_inputs) in
-        query.iter_mut()
+    for (
+        input,
+        mut controller,
+        mut player_facing,
+        mut air_counters,
+        mut deflect_state,
+        mut jump_input_buffer,
+        mut double_click_inputs,
+        mut sensor,
+        ghost_sensor,
+        mut fall_through_helper,
+        mut dropping_through_platforms,
+        zone_effects,
+    ) in query.iter_mut()
     {
         let controller = controller.as_mut();
         air_counters.update(controller);
+        deflect_state.update(time.delta());
+        jump_input_buffer.update(controller, time.delta());
         double_click_inputs.update(time.delta());
 
-        let desired_velocity = if let Some(axis_pair) = input.clamped_axis_pair(PlayerAction::Run) {
-            if axis_pair.x() <= -0.1 {
-                *player_facing = PlayerFacing::Left;
-                double_click_inputs.left.update_pressed();
-            } else if 0.1 <= axis_pair.x() {
-                *player_facing = PlayerFacing::Right;
-                double_click_inputs.right.update_pressed();
-            }
-            Vec3::X * 20.0 * axis_pair.x()
+        let holding_down = input
+            .clamped_axis_pair(PlayerAction::Run)
+            .is_some_and(|axis_pair| axis_pair.y() <= -0.5);
+        let mut fall_through_helper =
+            fall_through_helper.with(&mut sensor, ghost_sensor, MIN_PLATFORM_PROXIMITY);
+        if holding_down && (dropping_through_platforms.0 || input.just_pressed(PlayerAction::Jump))
+        {
+            dropping_through_platforms.0 =
+                fall_through_helper.try_falling(input.just_pressed(PlayerAction::Jump));
         } else {
-            Vec3::ZERO
-        };
+            dropping_through_platforms.0 = false;
+            fall_through_helper.dont_fall();
+        }
+
+        let mut desired_velocity =
+            if let Some(axis_pair) = input.clamped_axis_pair(PlayerAction::Run) {
+                if axis_pair.x() <= -0.1 {
+                    *player_facing = PlayerFacing::Left;
+                    double_click_inputs.left.update_pressed();
+                } else if 0.1 <= axis_pair.x() {
+                    *player_facing = PlayerFacing::Right;
+                    double_click_inputs.right.update_pressed();
+                }
+                Vec3::X * RUN_SPEED * axis_pair.x()
+            } else {
+                Vec3::ZERO
+            };
+
+        let default_zone_effects = ZoneEffects::default();
+        let zone_effects = zone_effects.unwrap_or(&default_zone_effects);
+        let up = zone_effects.up();
+        sensor.cast_direction = -up;
+        desired_velocity += zone_effects.wind.x * Vec3::X;
+
+        let ground_surface = sensor
+            .output
+            .as_ref()
+            .and_then(|ground| surfaces_query.get(ground.entity).ok());
+        let mut acceleration = TnuaBuiltinWalk::default().acceleration;
+        let mut jump_factor = 1.0;
+        match ground_surface {
+            Some((BlockSurface::Ice, _)) => {
+                acceleration *= 0.1;
+            }
+            Some((BlockSurface::Sticky, _)) => {
+                desired_velocity *= 0.5;
+                jump_factor = 0.6;
+            }
+            Some((BlockSurface::Conveyor { speed }, transform)) => {
+                desired_velocity += *speed * transform.right();
+            }
+            _ => {}
+        }
+
         controller.basis(TnuaBuiltinWalk {
             desired_velocity,
-            float_height: 1.5,
+            acceleration,
+            float_height: FLOAT_HEIGHT,
             cling_distance: 0.5,
-            up: Vec3::Y,
+            up,
+            coyote_time: jump_assist_settings.coyote_time,
+            free_fall_extra_gravity: TnuaBuiltinWalk::default().free_fall_extra_gravity
+                * zone_effects.gravity_scale.abs(),
             ..Default::default()
         });
-        if let Some(jump) = Some(input.clamped_value(PlayerAction::Jump)).filter(|jump| 0.0 < *jump)
+
+        let jump_possible = air_counters.jump_count() == 1
+            || !controller
+                .concrete_basis::<TnuaBuiltinWalk>()
+                .is_some_and(|(basis, state)| basis.is_airborne(state));
+        if input.just_pressed(PlayerAction::Jump) && !dropping_through_platforms.0 && !jump_possible
         {
+            jump_input_buffer.press(jump_assist_settings.buffer_time);
+        }
+        // Holding the button only feeds the jump when it can start (or already did) - once the
+        // controller rejects a jump it won't retry it for as long as it keeps being fed.
+        let jump = if jump_input_buffer.should_jump(jump_possible) {
+            Some(1.0)
+        } else {
+            Some(input.clamped_value(PlayerAction::Jump)).filter(|jump| {
+                0.0 < *jump
+                    && !dropping_through_platforms.0
+                    && (jump_possible || is_jumping(controller))
+            })
+        }
+        .map(|jump| jump_factor * jump);
+        if let Some(jump) = jump {
             match air_counters.jump_count() {
                 1 => {
                     controller.named_action(
                         "air-jump",
                         TnuaBuiltinJump {
-                            height: 4.0 * jump,
+                            height: AIR_JUMP_HEIGHT * jump,
                             allow_in_air: true,
+                            input_buffer_time: 0.0,
                             ..Default::default()
                         },
                     );
                 }
                 _ => {
                     controller.action(TnuaBuiltinJump {
-                        height: 5.0 * jump,
+                        height: JUMP_HEIGHT * jump,
                         allow_in_air: false,
+                        input_buffer_time: 0.0,
                         ..Default::default()
                     });
                 }
@@ -253,7 +535,7 @@ fn apply_controls(
         ] {
             if double_click_input.is_active() {
                 controller.action(TnuaBuiltinDash {
-                    displacement: 10.0 * direction_x * Vec3::X,
+                    displacement: DASH_DISTANCE * direction_x * Vec3::X,
                     // desired_forward: todo!(),
                     allow_in_air: air_counters.dash_count() < 1,
                     speed: 120.0,
@@ -265,5 +547,36 @@ fn apply_controls(
                 });
             }
         }
+
+        // Dashing through a missile deflects it, so a dash always opens a deflect window.
+        if matches!(
+            controller.action_flow_status(),
+            TnuaActionFlowStatus::ActionStarted(TnuaBuiltinDash::NAME)
+        ) {
+            deflect_state.try_start();
+        } else if input.just_pressed(PlayerAction::Deflect) {
+            let airborne = controller
+                .concrete_basis::<TnuaBuiltinWalk>()
+                .is_some_and(|(basis, state)| basis.is_airborne(state));
+            if airborne {
+                if air_counters.deflects < 1 && deflect_state.try_start() {
+                    air_counters.deflects += 1;
+                }
+            } else {
+                deflect_state.try_start();
+            }
+        }
+    }
+}
+
+fn draw_deflect_windows(query: Query<(&GlobalTransform, &DeflectState)>, mut gizmos: Gizmos) {
+    for (transform, deflect_state) in query.iter() {
+        if deflect_state.is_deflecting() {
+            gizmos.circle_2d(
+                transform.translation().truncate(),
+                DEFLECT_RADIUS,
+                Color::ALICE_BLUE,
+            );
+        }
     }
 }
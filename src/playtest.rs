@@ -0,0 +1,170 @@
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_yoleck::exclusive_systems::{YoleckExclusiveSystemDirective, YoleckExclusiveSystemsQueue};
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol::VpeolCameraState;
+use bevy_yoleck::{yoleck_exclusive_system_cancellable, YoleckEditorSections};
+
+use crate::player::{IsPlayer, PlayerSlot};
+use crate::AppState;
+
+/// Lets the level designer pick a point in the level for the next playtest to start from.
+pub struct PlaytestFromHerePlugin;
+
+impl Plugin for PlaytestFromHerePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlaytestStartPosition>();
+        app.init_resource::<EditorCameraTransform>();
+        app.world
+            .resource_mut::<YoleckEditorSections>()
+            .0
+            .push(playtest_from_here_section.into());
+        app.add_systems(YoleckSchedule::Populate, mark_players_for_playtest_start);
+        app.add_systems(
+            Update,
+            move_players_to_playtest_start.run_if(in_state(AppState::Game)),
+        );
+        app.add_systems(OnExit(AppState::Editor), save_editor_camera_transform);
+        app.add_systems(
+            OnEnter(AppState::Editor),
+            (
+                restore_editor_camera_transform,
+                clear_playtest_start_position,
+            ),
+        );
+        app.add_systems(
+            Update,
+            despawn_playtest_leftovers.run_if(in_state(AppState::Editor)),
+        );
+    }
+}
+
+#[derive(Resource, Default)]
+struct PlaytestStartPosition(Option<Vec2>);
+
+#[derive(Resource, Default)]
+struct EditorCameraTransform(Option<Transform>);
+
+#[derive(Component)]
+struct MoveToPlaytestStart(Vec2);
+
+fn playtest_from_here_section(world: &mut World) -> impl FnMut(&mut World, &mut egui::Ui) {
+    let mut system_state = SystemState::<(
+        Res<State<YoleckEditorState>>,
+        ResMut<PlaytestStartPosition>,
+        ResMut<YoleckExclusiveSystemsQueue>,
+    )>::new(world);
+
+    move |world, ui| {
+        let (editor_state, mut playtest_start_position, mut exclusive_queue) =
+            system_state.get_mut(world);
+        if !matches!(editor_state.get(), YoleckEditorState::EditorActive) {
+            return;
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Play From Here").clicked() {
+                exclusive_queue.push_back(
+                    pick_playtest_start_position.pipe(yoleck_exclusive_system_cancellable),
+                );
+            }
+            if let Some(position) = playtest_start_position.0 {
+                ui.label(format!(
+                    "Next playtest starts at ({:.1}, {:.1})",
+                    position.x, position.y
+                ));
+                if ui.button("Clear").clicked() {
+                    playtest_start_position.0 = None;
+                }
+            }
+        });
+    }
+}
+
+fn pick_playtest_start_position(
+    mut ui: ResMut<YoleckUi>,
+    cameras_query: Query<&VpeolCameraState>,
+    buttons: Res<Input<MouseButton>>,
+    mut playtest_start_position: ResMut<PlaytestStartPosition>,
+) -> YoleckExclusiveSystemDirective {
+    ui.label("Click where the players should start");
+    if ui.ctx().is_pointer_over_area() {
+        return YoleckExclusiveSystemDirective::Listening;
+    }
+    let Some(position) = cameras_query.iter().find_map(|camera_state| {
+        let cursor_ray = camera_state.cursor_ray?;
+        let distance = cursor_ray.intersect_plane(Vec3::ZERO, Vec3::Z)?;
+        Some(cursor_ray.get_point(distance).truncate())
+    }) else {
+        return YoleckExclusiveSystemDirective::Listening;
+    };
+    if buttons.just_released(MouseButton::Left) {
+        playtest_start_position.0 = Some(position);
+        return YoleckExclusiveSystemDirective::Finished;
+    }
+    YoleckExclusiveSystemDirective::Listening
+}
+
+fn mark_players_for_playtest_start(
+    mut populate: YoleckPopulate<&PlayerSlot, With<IsPlayer>>,
+    playtest_start_position: Res<PlaytestStartPosition>,
+) {
+    let Some(start_position) = playtest_start_position.0 else {
+        return;
+    };
+    populate.populate(|ctx, mut cmd, player_slot| {
+        if ctx.is_in_editor() || !ctx.is_first_time() {
+            return;
+        }
+        cmd.insert(MoveToPlaytestStart(
+            start_position + 2.0 * player_slot.index as f32 * Vec2::X,
+        ));
+    });
+}
+
+fn move_players_to_playtest_start(
+    mut query: Query<(Entity, &MoveToPlaytestStart, &mut Transform)>,
+    mut commands: Commands,
+) {
+    for (entity, move_to, mut transform) in query.iter_mut() {
+        transform.translation = move_to.0.extend(transform.translation.z);
+        commands.entity(entity).remove::<MoveToPlaytestStart>();
+    }
+}
+
+fn save_editor_camera_transform(
+    cameras_query: Query<&Transform, With<VpeolCameraState>>,
+    mut editor_camera_transform: ResMut<EditorCameraTransform>,
+) {
+    editor_camera_transform.0 = cameras_query.get_single().ok().copied();
+}
+
+fn restore_editor_camera_transform(
+    mut cameras_query: Query<&mut Transform, With<VpeolCameraState>>,
+    mut editor_camera_transform: ResMut<EditorCameraTransform>,
+) {
+    let Some(saved_transform) = editor_camera_transform.0.take() else {
+        return;
+    };
+    if let Ok(mut camera_transform) = cameras_query.get_single_mut() {
+        *camera_transform = saved_transform;
+    }
+}
+
+fn clear_playtest_start_position(mut playtest_start_position: ResMut<PlaytestStartPosition>) {
+    playtest_start_position.0 = None;
+}
+
+/// Missiles and explosions launched during the last frames of a playtest may get spawned after
+/// their level was already unloaded, so Yoleck won't clean them up.
+fn despawn_playtest_leftovers(
+    query: Query<(Entity, &YoleckBelongsToLevel)>,
+    levels_query: Query<(), With<YoleckKeepLevel>>,
+    mut commands: Commands,
+) {
+    for (entity, belongs_to_level) in query.iter() {
+        if !levels_query.contains(belongs_to_level.level) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use bevy_egui::egui;
+use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dRotatation, Vpeol3dScale};
+use bevy_yoleck::YoleckEditorSections;
+use ordered_float::OrderedFloat;
+
+/// Adds a section to the editor for configuring how edited entities snap into place.
+pub struct SnappingPlugin;
+
+impl Plugin for SnappingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorSnapping>();
+        app.world
+            .resource_mut::<YoleckEditorSections>()
+            .0
+            .push(snapping_section.into());
+    }
+}
+
+#[derive(Resource)]
+pub struct EditorSnapping {
+    pub snap_to_grid: bool,
+    pub grid_size: f32,
+    pub snap_angles: bool,
+    pub angle_step_degrees: f32,
+    /// Snap edges to the edges of nearby blocks, if they are closer than `neighbor_distance`.
+    pub snap_to_neighbors: bool,
+    pub neighbor_distance: f32,
+}
+
+impl Default for EditorSnapping {
+    fn default() -> Self {
+        Self {
+            snap_to_grid: true,
+            grid_size: 0.5,
+            snap_angles: true,
+            angle_step_degrees: 15.0,
+            snap_to_neighbors: true,
+            neighbor_distance: 0.5,
+        }
+    }
+}
+
+/// Only blocks that are this close along the other axis are considered for edge snapping.
+const NEIGHBOR_RANGE: f32 = 5.0;
+
+const GUIDE_COLOR: Color = Color::CYAN;
+
+fn snapping_section(world: &mut World) -> impl FnMut(&mut World, &mut egui::Ui) {
+    let mut system_state = SystemState::<ResMut<EditorSnapping>>::new(world);
+
+    move |world, ui| {
+        let mut snapping = system_state.get_mut(world);
+        ui.collapsing("Snapping", |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut snapping.snap_to_grid, "Grid");
+                ui.add_enabled(
+                    snapping.snap_to_grid,
+                    egui::DragValue::new(&mut snapping.grid_size)
+                        .speed(0.05)
+                        .clamp_range(0.05..=10.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut snapping.snap_angles, "Angles");
+                ui.add_enabled(
+                    snapping.snap_angles,
+                    egui::DragValue::new(&mut snapping.angle_step_degrees)
+                        .speed(1.0)
+                        .clamp_range(1.0..=90.0)
+                        .suffix("°"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut snapping.snap_to_neighbors, "Neighboring Blocks");
+                ui.add_enabled(
+                    snapping.snap_to_neighbors,
+                    egui::DragValue::new(&mut snapping.neighbor_distance)
+                        .speed(0.05)
+                        .clamp_range(0.05..=5.0),
+                );
+            });
+        });
+    }
+}
+
+/// The axis aligned rectangle covering a rectangular entity.
+pub fn bounding_rect(
+    position: &Vpeol3dPosition,
+    scale: &Vpeol3dScale,
+    rotation: Option<&Vpeol3dRotatation>,
+) -> Rect {
+    let rotation = rotation.map_or(Quat::IDENTITY, |rotation| rotation.0);
+    let half_size = 0.5 * scale.0.truncate();
+    let half_extents = [Vec2::X, Vec2::Y]
+        .map(|axis| (rotation * (axis * half_size).extend(0.0)).truncate().abs())
+        .into_iter()
+        .sum::<Vec2>();
+    Rect::from_center_half_size(position.0.truncate(), half_extents)
+}
+
+impl EditorSnapping {
+    pub fn snap_angle(&self, angle: f32) -> f32 {
+        if self.snap_angles {
+            let step = self.angle_step_degrees.to_radians();
+            (angle / step).round() * step
+        } else {
+            angle
+        }
+    }
+
+    fn snap_to_grid(&self, value: f32) -> f32 {
+        if self.snap_to_grid {
+            (value / self.grid_size).round() * self.grid_size
+        } else {
+            value
+        }
+    }
+
+    pub fn snap_size(&self, size: Vec2) -> Vec2 {
+        Vec2::new(self.snap_to_grid(size.x), self.snap_to_grid(size.y))
+    }
+
+    /// Find the smallest shift that aligns one of the `edges` (on `axis`) with an edge of a
+    /// neighbor, and the neighbor it aligns with.
+    fn neighbor_snap(
+        &self,
+        axis: usize,
+        edges: &[f32],
+        rect: Rect,
+        neighbors: &[Rect],
+    ) -> Option<(f32, Rect)> {
+        if !self.snap_to_neighbors {
+            return None;
+        }
+        let other_axis = 1 - axis;
+        neighbors
+            .iter()
+            .filter(|neighbor| {
+                let gap = (neighbor.min[other_axis] - rect.max[other_axis])
+                    .max(rect.min[other_axis] - neighbor.max[other_axis]);
+                gap < NEIGHBOR_RANGE
+            })
+            .flat_map(|neighbor| {
+                edges.iter().flat_map(move |edge| {
+                    [neighbor.min[axis], neighbor.max[axis]]
+                        .map(|neighbor_edge| (neighbor_edge - edge, *neighbor))
+                })
+            })
+            .filter(|(shift, _)| shift.abs() < self.neighbor_distance)
+            .min_by_key(|(shift, _)| OrderedFloat(shift.abs()))
+    }
+
+    /// Where to move a rectangle to, so that its edges will be aligned with the grid or with the
+    /// edges of its neighbors.
+    pub fn snap_rect(&self, rect: Rect, neighbors: &[Rect], gizmos: &mut Gizmos) -> Vec2 {
+        let mut center = rect.center();
+        for axis in [0, 1] {
+            if let Some((shift, neighbor)) =
+                self.neighbor_snap(axis, &[rect.min[axis], rect.max[axis]], rect, neighbors)
+            {
+                center[axis] += shift;
+                let snapped_rect = Rect::from_center_size(center, rect.size());
+                let edge = if (snapped_rect.min[axis] - neighbor.min[axis]).abs() < 0.001
+                    || (snapped_rect.min[axis] - neighbor.max[axis]).abs() < 0.001
+                {
+                    snapped_rect.min[axis]
+                } else {
+                    snapped_rect.max[axis]
+                };
+                draw_guide(gizmos, axis, edge, snapped_rect.union(neighbor));
+            } else {
+                center[axis] = self.snap_to_grid(rect.min[axis]) + 0.5 * rect.size()[axis];
+            }
+        }
+        center
+    }
+
+    /// Where to move a dragged corner to, so that it will be aligned with the grid or with the
+    /// edges of its neighbors.
+    pub fn snap_corner(&self, corner: Vec2, neighbors: &[Rect], gizmos: &mut Gizmos) -> Vec2 {
+        let corner_rect = Rect::from_center_size(corner, Vec2::ZERO);
+        Vec2::from_array([0, 1].map(|axis| {
+            if let Some((shift, neighbor)) =
+                self.neighbor_snap(axis, &[corner[axis]], corner_rect, neighbors)
+            {
+                let edge = corner[axis] + shift;
+                draw_guide(gizmos, axis, edge, neighbor.union_point(corner));
+                edge
+            } else {
+                self.snap_to_grid(corner[axis])
+            }
+        }))
+    }
+}
+
+fn draw_guide(gizmos: &mut Gizmos, axis: usize, edge: f32, span: Rect) {
+    let other_axis = 1 - axis;
+    let [mut start, mut end] = [Vec3::Z; 2];
+    start[axis] = edge;
+    end[axis] = edge;
+    start[other_axis] = span.min[other_axis];
+    end[other_axis] = span.max[other_axis];
+    gizmos.line(start, end, GUIDE_COLOR);
+}
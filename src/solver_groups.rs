@@ -0,0 +1,4 @@
+use bevy_rapier2d::geometry::Group;
+
+pub const PLAYER: Group = Group::GROUP_1;
+pub const MISSILE: Group = Group::GROUP_2;
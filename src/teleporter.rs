@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, Uuid};
+use bevy_rapier2d::prelude::*;
+use bevy_yoleck::exclusive_systems::{YoleckExclusiveSystemDirective, YoleckExclusiveSystemsQueue};
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol::{vpeol_read_click_on_entity, VpeolWillContainClickableChildren};
+use bevy_yoleck::vpeol_3d::Vpeol3dPosition;
+use bevy_yoleck::{yoleck_exclusive_system_cancellable, yoleck_map_entity_to_uuid};
+use serde::{Deserialize, Serialize};
+
+use crate::missile::{MissileConfig, MissileTarget};
+use crate::player::IsPlayer;
+use crate::utils::{collision_started_events_both_ways, CachedPbrMaker};
+use crate::{AppState, During};
+
+pub struct TeleporterPlugin;
+
+impl Plugin for TeleporterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_yoleck_entity_type({
+            YoleckEntityType::new("Teleporter")
+                .with_uuid()
+                .with::<Vpeol3dPosition>()
+                .with::<Teleporter>()
+        });
+
+        app.add_yoleck_edit_system(edit_teleporter);
+
+        app.add_systems(YoleckSchedule::Populate, populate_teleporter);
+        app.add_systems(
+            Update,
+            draw_teleporter_links.run_if(in_state(AppState::Editor)),
+        );
+        app.add_systems(
+            Update,
+            (teleport, cool_down_teleports).in_set(During::Gameplay),
+        );
+    }
+}
+
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct Teleporter {
+    /// The teleporter that players entering this one come out of. For a two way pair, link each
+    /// teleporter to the other.
+    pub destination: Option<Uuid>,
+    pub teleports_missiles: bool,
+    /// Things that come out of this teleporter are turned to move in this direction. If `None`,
+    /// they keep their velocity.
+    pub exit_direction: Option<Vec2>,
+}
+
+/// Players and missiles that just came out of a teleporter can't use another one until this
+/// expires - otherwise they'd be sent right back by the teleporter they came out of.
+#[derive(Component)]
+struct TeleportCooldown(Timer);
+
+const TELEPORT_COOLDOWN: f32 = 0.5;
+pub const TELEPORTER_RADIUS: f32 = 1.0;
+const LINK_COLOR: Color = Color::VIOLET;
+
+fn populate_teleporter(
+    mut populate: YoleckPopulate<(), With<Teleporter>>,
+    mut pbr: CachedPbrMaker,
+) {
+    populate.populate(|ctx, mut cmd, ()| {
+        if ctx.is_first_time() {
+            cmd.insert(VpeolWillContainClickableChildren);
+            cmd.insert(VisibilityBundle::default());
+            let ring = cmd
+                .commands()
+                .spawn(pbr.make_pbr_with(
+                    || {
+                        Mesh::from(shape::Torus {
+                            radius: TELEPORTER_RADIUS,
+                            ring_radius: 0.15,
+                            ..Default::default()
+                        })
+                    },
+                    || Color::PURPLE.into(),
+                ))
+                .insert(Transform::from_rotation(Quat::from_rotation_x(
+                    std::f32::consts::FRAC_PI_2,
+                )))
+                .id();
+            cmd.add_child(ring);
+        }
+        cmd.insert(Collider::ball(TELEPORTER_RADIUS));
+        cmd.insert(Sensor);
+    });
+}
+
+fn edit_teleporter(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<(Entity, &mut Teleporter, &Vpeol3dPosition)>,
+    mut knobs: YoleckKnobs,
+    mut pbr: CachedPbrMaker,
+    mut exclusive_queue: ResMut<YoleckExclusiveSystemsQueue>,
+) {
+    let Ok((entity, mut teleporter, position)) = edit.get_single_mut() else {
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        let button = if let Some(destination) = teleporter.destination {
+            ui.button(format!("Destination: {}", destination.simple()))
+        } else {
+            ui.button("No Destination")
+        };
+        if button.clicked() {
+            exclusive_queue.push_back(
+                vpeol_read_click_on_entity::<With<Teleporter>>
+                    .pipe(yoleck_map_entity_to_uuid)
+                    .pipe(
+                        |In(destination): In<Option<Uuid>>,
+                         mut edit: YoleckEdit<&mut Teleporter>| {
+                            let Ok(mut teleporter) = edit.get_single_mut() else {
+                                return YoleckExclusiveSystemDirective::Finished;
+                            };
+                            if let Some(destination) = destination {
+                                teleporter.destination = Some(destination);
+                                YoleckExclusiveSystemDirective::Finished
+                            } else {
+                                YoleckExclusiveSystemDirective::Listening
+                            }
+                        },
+                    )
+                    .pipe(yoleck_exclusive_system_cancellable),
+            );
+        }
+        if teleporter.destination.is_some() && ui.button("Clear").clicked() {
+            teleporter.destination = None;
+        }
+    });
+    ui.checkbox(&mut teleporter.teleports_missiles, "Teleports Missiles");
+    let mut reorient = teleporter.exit_direction.is_some();
+    ui.checkbox(&mut reorient, "Reorient Exit Velocity");
+    if reorient != teleporter.exit_direction.is_some() {
+        teleporter.exit_direction = reorient.then_some(Vec2::Y);
+    }
+
+    let Some(exit_direction) = teleporter.exit_direction.as_mut() else {
+        return;
+    };
+    let mut knob = knobs.knob(("teleporter-exit-direction", entity));
+    if knob.is_new {
+        knob.cmd.insert(pbr.make_pbr_with(
+            || {
+                Mesh::from(shape::UVSphere {
+                    radius: 0.4,
+                    sectors: 10,
+                    stacks: 10,
+                })
+            },
+            || Color::VIOLET.into(),
+        ));
+    }
+    knob.cmd.insert(Transform::from_translation(
+        position.0 + 2.0 * TELEPORTER_RADIUS * exit_direction.extend(0.0),
+    ));
+    if let Some(new_knob_pos) = knob.get_passed_data::<Vec3>() {
+        if let Some(new_direction) = (*new_knob_pos - position.0).truncate().try_normalize() {
+            *exit_direction = new_direction;
+        }
+    }
+}
+
+fn draw_teleporter_links(
+    query: Query<(
+        &Teleporter,
+        Option<&YoleckEntityUuid>,
+        &GlobalTransform,
+        &YoleckBelongsToLevel,
+    )>,
+    mut gizmos: Gizmos,
+) {
+    let positions = query
+        .iter()
+        .filter_map(|(_, uuid, transform, belongs_to_level)| {
+            Some((
+                (belongs_to_level.level, uuid?.get()),
+                transform.translation(),
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+    for (teleporter, _, transform, belongs_to_level) in query.iter() {
+        if let Some(destination_position) = teleporter
+            .destination
+            .and_then(|destination| positions.get(&(belongs_to_level.level, destination)))
+        {
+            gizmos.line(transform.translation(), *destination_position, LINK_COLOR);
+        }
+        if let Some(exit_direction) = teleporter.exit_direction {
+            gizmos.line(
+                transform.translation(),
+                transform.translation() + 2.0 * TELEPORTER_RADIUS * exit_direction.extend(0.0),
+                LINK_COLOR,
+            );
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn teleport(
+    mut reader: EventReader<CollisionEvent>,
+    teleporters_query: Query<(
+        &Teleporter,
+        Option<&YoleckEntityUuid>,
+        &GlobalTransform,
+        &YoleckBelongsToLevel,
+    )>,
+    mut travelers_query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            Option<&mut MissileTarget>,
+            Has<TeleportCooldown>,
+        ),
+        Or<(With<IsPlayer>, With<MissileConfig>)>,
+    >,
+    mut commands: Commands,
+) {
+    let mut teleported_players = Vec::new();
+    for (e1, e2) in collision_started_events_both_ways(&mut reader) {
+        let Ok((teleporter, _, entrance_transform, belongs_to_level)) = teleporters_query.get(e2)
+        else {
+            continue;
+        };
+        let Some(destination) = teleporter.destination else {
+            continue;
+        };
+        let Ok((mut transform, mut velocity, missile_target, in_cooldown)) =
+            travelers_query.get_mut(e1)
+        else {
+            continue;
+        };
+        if in_cooldown || (missile_target.is_some() && !teleporter.teleports_missiles) {
+            continue;
+        }
+        let Some((exit, exit_transform)) = teleporters_query.iter().find_map(
+            |(exit, uuid, exit_transform, exit_belongs_to_level)| {
+                (uuid?.get() == destination
+                    && exit_belongs_to_level.level == belongs_to_level.level)
+                    .then_some((exit, exit_transform))
+            },
+        ) else {
+            continue;
+        };
+
+        transform.translation = exit_transform
+            .translation()
+            .truncate()
+            .extend(transform.translation.z);
+        if let Some(exit_direction) = exit.exit_direction {
+            velocity.linvel = exit_direction * velocity.linvel.length();
+        }
+        if let Some(mut missile_target) = missile_target {
+            if let Some(exit_direction) = exit.exit_direction {
+                transform.rotation = Quat::from_rotation_arc_2d(Vec2::X, exit_direction);
+            }
+            // The missile followed its target through, so it can look for it again.
+            if matches!(*missile_target, MissileTarget::LastSeenAt(_)) {
+                *missile_target = MissileTarget::None;
+            }
+        } else {
+            teleported_players.push((e1, entrance_transform.translation().truncate()));
+        }
+        commands
+            .entity(e1)
+            .insert(TeleportCooldown(Timer::from_seconds(
+                TELEPORT_COOLDOWN,
+                TimerMode::Once,
+            )));
+    }
+
+    // Missiles don't see where the player came out, so they head to the teleporter they entered.
+    for (_, _, missile_target, _) in travelers_query.iter_mut() {
+        let Some(mut missile_target) = missile_target else {
+            continue;
+        };
+        if let MissileTarget::Player(player_entity) = *missile_target {
+            if let Some((_, entrance)) = teleported_players
+                .iter()
+                .find(|(teleported, _)| *teleported == player_entity)
+            {
+                *missile_target = MissileTarget::LastSeenAt(*entrance);
+            }
+        }
+    }
+}
+
+fn cool_down_teleports(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut TeleportCooldown)>,
+    mut commands: Commands,
+) {
+    for (entity, mut cooldown) in query.iter_mut() {
+        if cooldown.0.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<TeleportCooldown>();
+        }
+    }
+}
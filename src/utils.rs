@@ -1,6 +1,9 @@
+use bevy::ecs::query::ReadOnlyWorldQuery;
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use bevy_yoleck::prelude::*;
+use bevy_yoleck::vpeol_3d::{Vpeol3dPosition, Vpeol3dRotatation, Vpeol3dScale};
 
 pub fn collision_started_events_both_ways<'a>(
     reader: &'a mut EventReader<CollisionEvent>,
@@ -41,3 +44,85 @@ impl CachedPbrMaker<'_, '_> {
         }
     }
 }
+
+/// Edit system for translucent zones that are drawn over the level. Keeps them behind the level
+/// geometry, so that they won't block clicks on it.
+pub fn keep_zone_behind_geometry<F: 'static + ReadOnlyWorldQuery>(
+    mut edit: YoleckEdit<&mut Vpeol3dPosition, F>,
+) {
+    let Ok(mut position) = edit.get_single_mut() else {
+        return;
+    };
+    position.0.z = -10.0;
+}
+
+type ResizableQuery = (
+    Option<&'static Vpeol3dRotatation>,
+    &'static mut Vpeol3dScale,
+    &'static mut Vpeol3dPosition,
+);
+
+/// Edit system for resizing rectangular entities by dragging their corners.
+pub fn resize_with_corner_knobs<F: 'static + ReadOnlyWorldQuery>(
+    mut edit: YoleckEdit<ResizableQuery, F>,
+    mut knobs: YoleckKnobs,
+    mut pbr: CachedPbrMaker,
+) {
+    resize_with_snapped_corner_knobs(
+        &mut edit,
+        &mut knobs,
+        &mut pbr,
+        |corner| corner,
+        |size| size,
+    );
+}
+
+/// Like [`resize_with_corner_knobs`], but `snap_corner` decides where the dragged corner goes and
+/// `snap_size` decides the resulting size (in the entity's own axes).
+pub fn resize_with_snapped_corner_knobs<F: 'static + ReadOnlyWorldQuery>(
+    edit: &mut YoleckEdit<ResizableQuery, F>,
+    knobs: &mut YoleckKnobs,
+    pbr: &mut CachedPbrMaker,
+    mut snap_corner: impl FnMut(Vec2) -> Vec2,
+    mut snap_size: impl FnMut(Vec2) -> Vec2,
+) {
+    let Ok((rotation, mut scale, mut position)) = edit.get_single_mut() else {
+        return;
+    };
+    let rotation = rotation.map_or(Quat::IDENTITY, |rotation| rotation.0);
+
+    let knob_pbr = pbr.make_pbr_with(
+        || Mesh::from(shape::Box::new(0.4, 0.4, 1.1)),
+        || Color::ORANGE.into(),
+    );
+
+    for (i, diagonal) in [
+        Vec2::new(1.0, 1.0),
+        Vec2::new(-1.0, 1.0),
+        Vec2::new(-1.0, -1.0),
+        Vec2::new(1.0, -1.0),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let offset = 0.5 * diagonal * scale.0.truncate();
+        let mut knob = knobs.knob(("resize-marker", i));
+        if knob.is_new {
+            knob.cmd.insert(knob_pbr.clone());
+        }
+        knob.cmd.insert(Transform::from_translation(
+            position.0 + rotation * offset.extend(0.0),
+        ));
+
+        if let Some(new_marker_pos) = knob.get_passed_data::<Vec3>() {
+            let new_marker_pos = snap_corner(new_marker_pos.truncate()).extend(new_marker_pos.z);
+            let other_corner = position.0 - rotation * offset.extend(0.0);
+            let size_f = (rotation.inverse() * (new_marker_pos - other_corner)).truncate();
+            let size_f = size_f * diagonal;
+            let size_f = Vec2::from_array(size_f.to_array().map(|coord| coord.max(0.0)));
+            let size_f = snap_size(size_f);
+            scale.0 = size_f.extend(1.0);
+            position.0 = other_corner + rotation * (0.5 * diagonal * size_f).extend(0.0);
+        }
+    }
+}